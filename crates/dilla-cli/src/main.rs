@@ -19,11 +19,13 @@
 //! Simple utility to use and test Dilla without WASM build.
 use clap::{Parser, Subcommand};
 use dilla_describer::describe as dilla_describe;
-use dilla_renderer::{render as dilla_render, DESIGN_SYSTEM};
+use dilla_renderer::{
+    engine::extract_translations, options::RenderOptions, render_profiled, render_with_options,
+    DESIGN_SYSTEM,
+};
 use html_parser::Dom;
 use std::fs;
 use std::path::PathBuf;
-use std::str;
 use std::time::Instant;
 
 #[doc(hidden)]
@@ -61,18 +63,30 @@ enum Commands {
             short,
             long,
             default_value_t = String::from("full"),
-            value_parser = clap::builder::PossibleValuesParser::new(["_logs", "_test", "_test_full", "full", "json", "dom_json"])
+            value_parser = clap::builder::PossibleValuesParser::new(["_logs", "_test", "_test_full", "full", "full_inline", "email", "json", "fragments", "dom", "dom_json"])
         )]
         mode: String,
         /// Optional, output result to a file instead of print
         #[clap(short, long, value_name = "FILE")]
         write: Option<String>,
-        /// Do not prettify the output, default 'false'
+        /// Do not minify the output, default 'false'
         #[clap(short, long, default_value_t = false)]
         raw: bool,
+        /// Indent the output instead of minify, default 'false'
+        #[clap(short, long, default_value_t = false)]
+        pretty: bool,
         /// Print less messages, default 'false'
         #[clap(short, long, default_value_t = false)]
         quiet: bool,
+        /// Print a summary of the render time by component, default 'false'
+        #[clap(long, default_value_t = false)]
+        profile: bool,
+        /// Optional seed of the template `random()`, default from the payload
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Optional locale, ie: fr-CA, default from the payload '@lang'
+        #[clap(long)]
+        locale: Option<String>,
     },
     /// Introspection query for a Design System
     Describe {
@@ -83,6 +97,12 @@ enum Commands {
         #[clap(default_value_t = String::from(""))]
         id: String,
     },
+    /// Extract the `'...'|t` terms of the templates to a translation catalog
+    Translations {
+        /// Optional, output the catalog to a file instead of print
+        #[clap(short, long, value_name = "FILE")]
+        write: Option<String>,
+    },
     Info {},
 }
 
@@ -99,6 +119,9 @@ enum Commands {
 ///
 /// The `Commands::Describe` variant calls the `describer::describe` function to provide
 /// introspection for the given artefact and ID.
+///
+/// The `Commands::Translations` variant prints or writes the translation
+/// catalog of the templates.
 /// Rust Command-line Interface (CLI) for performing rendering and introspection tasks.
 fn main() {
     let cli = Cli::parse();
@@ -108,11 +131,26 @@ fn main() {
             mode,
             write,
             raw,
+            pretty,
             quiet,
+            profile,
+            seed,
+            locale,
         } => {
-            render(payload, mode, write, raw.to_owned(), quiet.to_owned());
+            let mut options = RenderOptions::new()
+                .with_minify(!raw)
+                .with_pretty(pretty.to_owned())
+                .with_profiling(profile.to_owned());
+            if let Some(seed) = seed {
+                options = options.with_seed(*seed);
+            }
+            if let Some(locale) = locale {
+                options = options.with_locale(locale);
+            }
+            render(payload, mode, write, options, quiet.to_owned());
         }
         Commands::Describe { artefact, id } => describe(artefact, id),
+        Commands::Translations { write } => translations(write),
         Commands::Info {} => info(),
     }
 }
@@ -135,9 +173,15 @@ fn describe(artefact: &str, id: &str) {
 /// * `payload` - A `PathBuf` containing the path to the payload file.
 /// * `mode` - A string specifying the rendering mode.
 /// * `write` - An optional `String` specifying the path to write the output. If provided, the output will be written to the specified file.
-/// * `raw` - A boolean indicating whether to output the result without any formatting.
+/// * `options` - The render options, for the minify or pretty formatting.
 /// * `quiet` - A boolean indicating whether to suppress any additional output and only display the result.
-fn render(payload: &PathBuf, mode: &str, write: &Option<String>, raw: bool, quiet: bool) {
+fn render(
+    payload: &PathBuf,
+    mode: &str,
+    write: &Option<String>,
+    options: RenderOptions,
+    quiet: bool,
+) {
     let is_json = payload.display().to_string().ends_with(".json");
     if !is_json {
         return eprintln!("[Error] Payload is not a json file!");
@@ -150,19 +194,26 @@ fn render(payload: &PathBuf, mode: &str, write: &Option<String>, raw: bool, quie
         _ => (false, mode),
     };
 
+    let format = format_output.parse().expect("Unknown output format!");
+    let minify = options.minify && !options.pretty;
+    let profile = options.profiling;
+
     let now = Instant::now();
-    let mut result = dilla_render(&payload, format_output).expect("Dilla rendering failed!");
+    let (mut result, timing) = match profile {
+        true => render_profiled(&payload, format, options)
+            .map(|(result, timing)| (result, Some(timing))),
+        false => render_with_options(&payload, format, options).map(|result| (result, None)),
+    }
+    .expect("Dilla rendering failed!");
     let render = now.elapsed().as_micros() as f32 / 1000.0;
 
-    if !raw && !do_dom {
-        result = format_minify(result);
-    } else if do_dom {
+    if do_dom {
         result = format_dom_json(result);
     }
 
     let message = format!(
         "Dilla CLI v{VERSION} | ds: {}, minify: {}, render: {:.2} ms",
-        DESIGN_SYSTEM, !raw, render
+        DESIGN_SYSTEM, minify, render
     );
 
     if let Some(file_output) = write {
@@ -175,6 +226,39 @@ fn render(payload: &PathBuf, mode: &str, write: &Option<String>, raw: bool, quie
     } else {
         println!("<!-- {} -->\n{}\n<!-- {} -->", message, result, message);
     }
+
+    // Summary on stderr to keep the output usable.
+    if let Some(timing) = timing {
+        eprintln!("Timing (ms) | component x count (self time)");
+        for line in timing.lines() {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+/// Outputs a json catalog of the template terms to translate, as the payload
+/// `@trans` with each term as its own translation.
+///
+/// # Arguments
+///
+/// * `write` - An optional `String` specifying the path to write the catalog.
+fn translations(write: &Option<String>) {
+    let catalog: serde_json::Map<String, serde_json::Value> = extract_translations()
+        .into_iter()
+        .map(|term| (term.clone(), serde_json::Value::String(term)))
+        .collect();
+    let result = serde_json::to_string_pretty(&catalog).expect("Failed to format catalog");
+
+    if let Some(file_output) = write {
+        fs::write(file_output, result).expect("Failed to write file");
+        println!(
+            "Catalog generated: {} ({} terms)",
+            file_output,
+            catalog.len()
+        );
+    } else {
+        println!("{}", result);
+    }
 }
 
 #[doc(hidden)]
@@ -189,14 +273,3 @@ fn format_dom_json(s: String) -> String {
         .to_json_pretty()
         .expect("Failed to format DOM as JSON")
 }
-
-#[doc(hidden)]
-fn format_minify(s: String) -> String {
-    let mut html_minifier = html_minifier::HTMLMinifier::new();
-    html_minifier.set_remove_comments(false);
-    html_minifier.digest(s.clone()).unwrap();
-
-    str::from_utf8(html_minifier.get_html())
-        .unwrap()
-        .to_string()
-}
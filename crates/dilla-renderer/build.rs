@@ -6,6 +6,9 @@ mod ds;
 #[path = "src/build/test.rs"]
 mod test;
 
+#[path = "src/build/integrity.rs"]
+mod integrity;
+
 use const_gen::*;
 use std::collections::HashMap;
 use std::fs::File;
@@ -17,6 +20,8 @@ fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=src/build/ds.rs");
     println!("cargo:rerun-if-env-changed=DS");
+    println!("cargo:rerun-if-env-changed=DILLA_ASSETS_DIR");
+    println!("cargo:rerun-if-env-changed=DILLA_SRI_ALLOW_MISSING");
 
     ds::config();
 
@@ -44,6 +49,7 @@ fn main() {
 
     let templates_path = format!("{ds_path}/components");
     minijinja_embed::embed_templates!(&templates_path);
+    build_templates_names(&templates_path);
 
     if design_system != "test" {
         build_tests(&design_system, &ds_path, &root_path);
@@ -83,8 +89,20 @@ struct SystemConfig {
     pub variables: HashMap<&'static str, &'static str>,
 }
 
-// Helper to wrap the config build.
+// Helper to wrap the config build, used by the Design System `ds.rs`.
+#[allow(dead_code)]
 fn build_config(config: SystemConfig) {
+    build_config_with_integrity(config, HashMap::new());
+}
+
+// Config build with the Subresource Integrity hashes declared for libraries
+// that can not be computed, as remote ones.
+fn build_config_with_integrity(
+    mut config: SystemConfig,
+    declared: HashMap<&'static str, &'static str>,
+) {
+    let libraries_integrity = build_integrity(&mut config, declared);
+
     let out_dir: std::ffi::OsString = env::var_os("OUT_DIR").unwrap();
     let dest_path: std::path::PathBuf = Path::new(&out_dir).join("codegen_config.rs");
 
@@ -99,12 +117,179 @@ fn build_config(config: SystemConfig) {
         const_definition!(pub(crate) SystemConfig),
         format!("#[doc = \"The current Design System configuration.\"]"),
         const_declaration!(pub(crate) DEFINITION = config),
+        format!("#[doc = \"Subresource Integrity hashes as `url => sha384-...` for libraries.\"]"),
+        const_declaration!(pub(crate) LIBRARIES_INTEGRITY = libraries_integrity),
     ]
     .join("\n");
 
     fs::write(dest_path, contents).unwrap();
 }
 
+// Compute the Subresource Integrity hashes for local library files and add
+// `integrity` and `crossorigin` to the libraries of the config.
+// Remote libraries can not be hashed and must declare their own `integrity`.
+// A remote library without integrity or a missing local file fails the build,
+// unless `DILLA_SRI_ALLOW_MISSING` is set to only warn. The libraries of the
+// test Design System are placeholders, not reported.
+fn build_integrity(
+    config: &mut SystemConfig,
+    mut declared: HashMap<&'static str, &'static str>,
+) -> HashMap<&'static str, &'static str> {
+    let is_test = env::var("DS").unwrap_or_else(|_| "test".to_string()) == "test";
+    let allow_missing = env::var("DILLA_SRI_ALLOW_MISSING").is_ok_and(|v| v == "1" || v == "true");
+
+    // Integrity declared in the js attributes is kept as is.
+    let mut urls: Vec<&'static str> = Vec::new();
+    for (url, attributes) in all_libraries_js(config) {
+        if let Some(integrity) = attributes.get("integrity") {
+            declared.insert(url, integrity);
+        }
+        urls.push(url);
+    }
+    for html in all_libraries_css_html(config) {
+        urls.extend(css_hrefs(html));
+    }
+
+    let computed = integrity::compute(&urls, &declared, Path::new(&assets_path()));
+    for file in &computed.files {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+    // Libraries of the test Design System are placeholders, not reported.
+    for message in computed.missing.iter().filter(|_| !is_test) {
+        if !allow_missing {
+            panic!("[Fatal] {message}, set DILLA_SRI_ALLOW_MISSING=1 to build without it.");
+        }
+        println!("cargo:warning={message}");
+    }
+
+    let integrity = computed.hashes;
+    let add_integrity =
+        |libraries: &mut Vec<(&'static str, HashMap<&'static str, &'static str>)>| {
+            for (url, attributes) in libraries.iter_mut() {
+                if let Some(hash) = integrity.get(*url) {
+                    attributes.entry("integrity").or_insert(*hash);
+                    attributes.entry("crossorigin").or_insert("anonymous");
+                }
+            }
+        };
+    add_integrity(&mut config.default_libraries_js);
+    config.libraries_js.values_mut().for_each(add_integrity);
+    config
+        .components_library_js
+        .values_mut()
+        .for_each(add_integrity);
+
+    config.default_libraries_css_html =
+        css_html_with_integrity(config.default_libraries_css_html, &integrity);
+    for html in config.libraries_css_html.values_mut() {
+        *html = css_html_with_integrity(html, &integrity);
+    }
+    for html in config.components_library_css_html.values_mut() {
+        *html = css_html_with_integrity(html, &integrity);
+    }
+
+    integrity
+}
+
+// Local assets root, default to the Design System folder.
+fn assets_path() -> String {
+    if let Ok(path) = env::var("DILLA_ASSETS_DIR") {
+        return path;
+    }
+    let design_system = env::var("DS").unwrap_or_else(|_| "test".to_string());
+    if design_system == "test" {
+        return "./tests".to_string();
+    }
+    let tpl_dir = env::var("DILLA_TPL_DIR").unwrap_or_else(|_| "../../var/run/".to_string());
+    format!("{tpl_dir}{design_system}")
+}
+
+fn all_libraries_js(
+    config: &SystemConfig,
+) -> Vec<(&'static str, HashMap<&'static str, &'static str>)> {
+    config
+        .default_libraries_js
+        .iter()
+        .chain(config.libraries_js.values().flatten())
+        .chain(config.components_library_js.values().flatten())
+        .cloned()
+        .collect()
+}
+
+fn all_libraries_css_html(config: &SystemConfig) -> Vec<&'static str> {
+    let mut html = vec![config.default_libraries_css_html];
+    html.extend(config.libraries_css_html.values());
+    html.extend(config.components_library_css_html.values());
+    html
+}
+
+// Extract the `href` of the `<link>` tags of a css html config string.
+// Quotes in the config strings can be escaped as they are written as is in
+// the generated code.
+fn css_hrefs(html: &'static str) -> Vec<&'static str> {
+    html.split("href=")
+        .skip(1)
+        .filter_map(|part| {
+            let part = part.trim_start_matches('\\').strip_prefix('"')?;
+            part.split(['"', '\\']).next()
+        })
+        .collect()
+}
+
+// Add `integrity` and `crossorigin` to each `<link>` with a known hash.
+fn css_html_with_integrity(
+    html: &'static str,
+    integrity: &HashMap<&'static str, &'static str>,
+) -> &'static str {
+    let mut result = String::with_capacity(html.len());
+    let mut changed = false;
+    for tag in html.split_inclusive('>') {
+        let hash = css_hrefs(tag).first().and_then(|href| integrity.get(href));
+        let Some(hash) = hash.filter(|_| !tag.contains("integrity=")) else {
+            result.push_str(tag);
+            continue;
+        };
+        let quote = if tag.contains("\\\"") { "\\\"" } else { "\"" };
+        let mut extra = format!(" integrity={quote}{hash}{quote}");
+        if !tag.contains("crossorigin=") {
+            extra.push_str(&format!(" crossorigin={quote}anonymous{quote}"));
+        }
+        let end = tag.len() - if tag.ends_with(" />") { 3 } else { 1 };
+        result.push_str(&tag[..end]);
+        result.push_str(&extra);
+        result.push_str(&tag[end..]);
+        changed = true;
+    }
+    if !changed {
+        return html;
+    }
+    Box::leak(result.into_boxed_str())
+}
+
+// List the names of the embedded templates, as minijinja_embed names them.
+fn build_templates_names(templates_path: &str) {
+    let root = Path::new(templates_path).canonicalize().unwrap();
+    let mut names: Vec<String> = walkdir::WalkDir::new(&root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let name = entry.path().strip_prefix(&root).ok()?;
+            Some(name.to_string_lossy().replace('\\', "/"))
+        })
+        .collect();
+    names.sort();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("templates_names.rs");
+    let contents = format!(
+        "#[allow(clippy::redundant_static_lifetimes)]\nconst TEMPLATES_NAMES: &'static [&'static str] = &{names:?};\n"
+    );
+    fs::write(path, contents).unwrap();
+}
+
 // build the tests code for this DS.
 // @todo: list of missing tests?
 fn build_tests(design_system: &str, ds_path: &str, root_path: &str) {
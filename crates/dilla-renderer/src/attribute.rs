@@ -3,12 +3,15 @@
 //! An HTML attribute is managed and created to be rendered with an element or
 //! from inside a MiniJinja template. **class** and **style** have some specific
 //! management as they can come from scoped properties.
-//! Other attributes are managed without any process.
+//! Other attributes keep the type of their value, see [`AttributeValue`].
+//!
+//! Values are escaped when rendered, except values added as safe strings which
+//! are already escaped. Attributes with an invalid name are never added.
 
 // @todo see https://docs.rs/html5ever/latest/html5ever/struct.Attribute.html
 // @todo check https://docs.rs/html_parser/0.7.0/src/html_parser/dom/mod.rs.html#328
 
-use crate::scoped::Scoped;
+use crate::{options::RenderOptions, policy::SecurityPolicy, scoped::Scoped};
 
 use indexmap::IndexMap;
 use minijinja::{
@@ -17,27 +20,169 @@ use minijinja::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Map;
+use std::collections::HashSet;
 use std::fmt;
 
 pub const KEY_ATTRIBUTES: &str = "attributes";
 
+/// HTML boolean attributes, rendered without value when set and omitted when
+/// `false`.
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+/// Attributes with a list of space separated tokens, merged when added.
+const TOKEN_LIST_ATTRIBUTES: &[&str] = &["class", "rel"];
+
+/// Typed value of an HTML attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    /// Bare attribute when `true`, omitted when `false` for the HTML boolean
+    /// attributes. Rendered as `"true"` or `"false"` text for the others, as
+    /// `draggable`, `spellcheck` or `data-*`.
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    /// Space separated tokens, as `class` or `rel`.
+    Tokens(Vec<String>),
+    /// Style declarations as property and value, merged by property. The
+    /// property is empty for a part without declaration.
+    Style(Vec<(String, String)>),
+}
+
+impl AttributeValue {
+    /// Build a value from strings, as a list for `class`, `rel`, `style` or
+    /// multiple values.
+    pub fn from_strings(name: &str, values: Vec<String>) -> Self {
+        match name {
+            "style" => {
+                let mut declarations = Vec::new();
+                for value in values {
+                    merge_declarations(&mut declarations, parse_style(&value));
+                }
+                AttributeValue::Style(declarations)
+            }
+            _ if TOKEN_LIST_ATTRIBUTES.contains(&name) || values.len() != 1 => {
+                AttributeValue::Tokens(values)
+            }
+            _ => AttributeValue::String(values.into_iter().next().unwrap_or_default()),
+        }
+    }
+
+    /// Get the values as strings.
+    pub fn strings(&self) -> Vec<String> {
+        match self {
+            AttributeValue::Bool(value) => vec![value.to_string()],
+            AttributeValue::Number(value) => vec![value.to_string()],
+            AttributeValue::String(value) => vec![value.clone()],
+            AttributeValue::Tokens(values) => values.clone(),
+            AttributeValue::Style(declarations) => declarations
+                .iter()
+                .map(|(property, value)| match property.is_empty() {
+                    true => value.clone(),
+                    false => format!("{property}: {value};"),
+                })
+                .collect(),
+        }
+    }
+
+    fn map_strings(&mut self, f: impl Fn(&str) -> String) {
+        match self {
+            AttributeValue::String(value) => *value = f(value),
+            AttributeValue::Tokens(values) => {
+                for value in values.iter_mut() {
+                    *value = f(value);
+                }
+            }
+            AttributeValue::Style(declarations) => {
+                for (property, value) in declarations.iter_mut() {
+                    *property = f(property);
+                    *value = f(value);
+                }
+            }
+            AttributeValue::Bool(_) | AttributeValue::Number(_) => (),
+        }
+    }
+
+    /// Add the values of a list, other values are replaced.
+    fn merge(&mut self, other: AttributeValue) {
+        match (self, other) {
+            (AttributeValue::Tokens(values), other) => values.extend(other.strings()),
+            (AttributeValue::Style(declarations), other) => {
+                for value in other.strings() {
+                    merge_declarations(declarations, parse_style(&value));
+                }
+            }
+            (this, other) => *this = other,
+        }
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.strings().join(" "))
+    }
+}
+
+impl From<AttributeValue> for serde_json::Value {
+    fn from(value: AttributeValue) -> Self {
+        match value {
+            AttributeValue::Bool(value) => serde_json::Value::Bool(value),
+            AttributeValue::Number(value) => serde_json::Value::Number(value),
+            AttributeValue::String(value) => serde_json::Value::String(value),
+            value @ AttributeValue::Style(_) => value.strings().into(),
+            AttributeValue::Tokens(values) => values.into(),
+        }
+    }
+}
+
 /// An Attribute support data to generate HTML attribute markup.
 /// @todo IndexMap is needed to ensure working test, as it can have a performance impact this could switch only for tests.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Attribute {
-    attrs: IndexMap<String, Vec<String>>,
+    attrs: IndexMap<String, AttributeValue>,
+    /// Names of the attributes with values already escaped.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    safe: HashSet<String>,
 }
 
 impl Attribute {
     pub fn new() -> Self {
         Attribute {
             attrs: IndexMap::new(),
+            safe: HashSet::new(),
         }
     }
 
-    /// Returns a slice reference to the attribute values for the given name.
+    /// Returns the attribute values for the given name, as strings.
     ///
-    /// If the attribute does not exist, returns an empty slice.
+    /// Typed values are converted, see [`AttributeValue`]. If the attribute
+    /// does not exist, returns an empty Vec.
     ///
     /// # Arguments
     ///
@@ -45,7 +190,7 @@ impl Attribute {
     ///
     /// # Returns
     ///
-    /// A slice reference to the attribute values, or empty slice if not found.
+    /// An owned Vec of the attribute values, or an empty Vec if not found.
     ///
     /// # Examples
     ///
@@ -61,11 +206,30 @@ impl Attribute {
     /// let class = attribute.get_attr("class");
     /// assert_eq!(class, &["foo", "bar"]);
     /// ```
-    pub fn get_attr(&self, name: &str) -> &[String] {
-        match self.attrs.get(name) {
-            Some(vec) => vec,
-            None => &[],
-        }
+    pub fn get_attr(&self, name: &str) -> Vec<String> {
+        self.attrs
+            .get(name)
+            .map(AttributeValue::strings)
+            .unwrap_or_default()
+    }
+
+    /// Returns the typed value of an attribute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::{Attribute, AttributeValue};
+    /// use serde_json::json;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr_from_serde(&json!({"hidden": false, "data-num": 45}));
+    ///
+    /// assert_eq!(attribute.get_value("hidden"), Some(&AttributeValue::Bool(false)));
+    /// assert_eq!(attribute.get_value("data-num"), Some(&AttributeValue::Number(45.into())));
+    /// assert_eq!(attribute.to_string(), " data-num=\"45\"");
+    /// ```
+    pub fn get_value(&self, name: &str) -> Option<&AttributeValue> {
+        self.attrs.get(name)
     }
 
     /// Build and update HTML attributes based on data, scoped properties.
@@ -111,6 +275,41 @@ impl Attribute {
     ///
     /// A modified [`Attribute`] object with updated HTML attributes.
     pub fn build_scoped(&mut self, data: &Map<String, serde_json::Value>) {
+        self.build_scoped_with_options(data, &RenderOptions::default());
+    }
+
+    /// Build and update HTML attributes based on data, scoped properties and
+    /// render options.
+    ///
+    /// With option `scoped_local_variables`, `@local_variables` are set as a
+    /// class instead of a `style` attribute, the stylesheet is rendered with
+    /// the bubbable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    /// use dilla_renderer::options::RenderOptions;
+    /// use serde_json::{json, Map};
+    ///
+    /// let mut attribute = Attribute::new();
+    /// let mut data = Map::new();
+    /// data.insert(
+    ///   format!("@local_variables"),
+    ///   json!({"var-1": "#222"}),
+    /// );
+    ///
+    /// let options = RenderOptions::new().with_scoped_local_variables(true);
+    /// attribute.build_scoped_with_options(&data, &options);
+    ///
+    /// assert!(!attribute.has_attribute("style".into()));
+    /// assert!(attribute.has_class("dilla-lv-3690cbaa".into()));
+    /// ```
+    pub fn build_scoped_with_options(
+        &mut self,
+        data: &Map<String, serde_json::Value>,
+        options: &RenderOptions,
+    ) {
         // Collect scoped to be used for attributes build.
         let mut scoped = Scoped::new();
         scoped.collect(data);
@@ -118,17 +317,18 @@ impl Attribute {
         // Add class from '@styles' if any.
         match !scoped.styles.is_empty() {
             true => {
-                self.add_attr("class", scoped.styles);
+                self.add_attr("class", scoped.styles.clone());
             }
             false => (),
         }
 
-        // Add style attribute values from '@local_variables' if any.
-        match !scoped.local_variables.is_empty() {
-            true => {
-                self.add_attr("style", scoped.local_variables);
+        // Add style attribute values or class from '@local_variables' if any.
+        if options.scoped_local_variables {
+            if let Some(class) = scoped.local_variables_class() {
+                self.add_attr("class", vec![class]);
             }
-            false => (),
+        } else if !scoped.local_variables.is_empty() {
+            self.add_attr("style", scoped.local_variables);
         }
 
         // Add theme attribute values from '@theme' if any.
@@ -185,6 +385,35 @@ impl Attribute {
     ///
     /// A modified [`Attribute`] object with updated HTML attributes.
     pub fn build_attributes(&mut self, attributes: &serde_json::Value) {
+        self.build_attributes_with_policy(attributes, &SecurityPolicy::default());
+    }
+
+    /// Process the HTML attributes based on the provided data, without the
+    /// attributes not allowed by the security policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    /// use dilla_renderer::policy::SecurityPolicy;
+    /// use serde_json::json;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// let attr = json!({
+    ///   "href": "javascript:alert(1)",
+    ///   "onClick": "alert(1)",
+    ///   "title": "foo"
+    /// });
+    ///
+    /// attribute.build_attributes_with_policy(&attr, &SecurityPolicy::strict());
+    ///
+    /// assert_eq!(attribute.to_string(), " title=\"foo\"");
+    /// ```
+    pub fn build_attributes_with_policy(
+        &mut self,
+        attributes: &serde_json::Value,
+        policy: &SecurityPolicy,
+    ) {
         if !attributes.is_object() {
             return;
         }
@@ -206,15 +435,18 @@ impl Attribute {
         }
 
         if let Some(serde_json::Value::String(style)) = attributes.get("style") {
-            if style.ends_with(';') {
-                self.add_attr("style", vec![style]);
-            } else {
-                self.add_attr("style", vec![format!("{};", style)]);
-            };
+            self.add_attr("style", vec![style]);
             other_data.remove("style");
         }
 
         self.add_attr_from_serde(&serde_json::Value::Object(other_data));
+        self.retain_allowed(policy);
+    }
+
+    /// Remove the attributes not allowed by the security policy.
+    pub fn retain_allowed(&mut self, policy: &SecurityPolicy) {
+        self.attrs
+            .retain(|name, value| policy.allows_attribute(name, &value.to_string()));
     }
 
     /// Add attributes by name and value.
@@ -240,13 +472,80 @@ impl Attribute {
     /// assert!(!attribute.has_class("unknown".into()));
     /// ```
     pub fn add_attr(&mut self, name: &str, values: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.insert(name, strings_value(name, values), false);
+    }
+
+    /// Add an attribute with a typed value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::{Attribute, AttributeValue};
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_value("disabled", AttributeValue::Bool(true));
+    /// attribute.add_value("aria-hidden", AttributeValue::Bool(true));
+    /// attribute.add_value("hidden", AttributeValue::Bool(false));
+    /// attribute.add_value("draggable", AttributeValue::Bool(false));
+    /// attribute.add_value("spellcheck", AttributeValue::Bool(true));
+    ///
+    /// assert_eq!(
+    ///     attribute.to_string(),
+    ///     " disabled aria-hidden=\"true\" draggable=\"false\" spellcheck=\"true\""
+    /// );
+    /// ```
+    pub fn add_value(&mut self, name: &str, value: AttributeValue) {
+        self.insert(name, value, false);
+    }
+
+    /// Add attributes by name with values already escaped, rendered as is.
+    ///
+    /// Existing values of the attribute are escaped to be merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr("title", vec!["Tom & Jerry"]);
+    /// attribute.add_safe_attr("data-text", vec!["&lt;b&gt;"]);
+    ///
+    /// assert_eq!(
+    ///     attribute.to_string(),
+    ///     " title=\"Tom &amp; Jerry\" data-text=\"&lt;b&gt;\""
+    /// );
+    /// ```
+    pub fn add_safe_attr(&mut self, name: &str, values: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.insert(name, strings_value(name, values), true);
+    }
+
+    fn insert(&mut self, name: &str, mut value: AttributeValue, safe: bool) {
+        if !is_valid_name(name) {
+            return;
+        }
         // Replace attribute instead of adding, this is the set_attribute default behavior.
-        if name != "class" && name != "style" && self.attrs.contains_key(name) {
-            self.attrs.swap_remove(name);
+        if !is_list(name) && self.attrs.contains_key(name) {
+            self.remove_attr_by_name(name);
+        }
+        let is_safe = self.safe.contains(name);
+        // A value merged in safe values must be escaped first.
+        if is_safe && !safe {
+            value.map_strings(escape);
+        }
+        match self.attrs.get_mut(name) {
+            Some(existing) => {
+                if safe && !is_safe {
+                    existing.map_strings(escape);
+                }
+                existing.merge(value);
+            }
+            None => {
+                self.attrs.insert(name.to_string(), value);
+            }
         }
-        let entry = self.attrs.entry(name.to_string()).or_default();
-        for value in values {
-            entry.push(value.as_ref().to_string());
+        if safe {
+            self.safe.insert(name.to_string());
         }
     }
 
@@ -325,12 +624,30 @@ impl Attribute {
         match values.kind() {
             ValueKind::Seq => {
                 let mut new_values: Vec<String> = Vec::new();
+                let mut safe = true;
                 if let Ok(iter) = values.try_iter() {
                     for val in iter {
+                        // We can have some undefined or empty value here.
+                        // @todo find source of undefined
+                        if val.to_string() == "undefined" {
+                            continue;
+                        }
+                        safe &= val.is_safe();
                         new_values.push(val.to_string())
                     }
                 }
-                self.add_attr(name, new_values);
+                let safe = safe && !new_values.is_empty();
+                self.insert(name, AttributeValue::from_strings(name, new_values), safe);
+            }
+            // Nested data are expanded as `data-*` attributes.
+            ValueKind::Map if name == "data" || name.starts_with("data-") => {
+                if let Ok(iter) = values.try_iter() {
+                    for key in iter {
+                        if let Ok(val) = values.get_item(&key) {
+                            self.add_attr_from_jinja(&format!("{name}-{key}"), val);
+                        }
+                    }
+                }
             }
             ValueKind::Map => {
                 let mut new_values: Vec<String> = Vec::new();
@@ -345,9 +662,19 @@ impl Attribute {
                     self.add_attr(name, new_values);
                 }
             }
-            // Every other type is cast as string to handle int, bool, float...
-            // @todo do we have a Map case?
-            _ => self.add_attr(name, &[values.to_string()]),
+            ValueKind::Bool => self.insert(name, AttributeValue::Bool(values.is_true()), false),
+            ValueKind::Number => match serde_json::to_value(&values) {
+                Ok(serde_json::Value::Number(number)) => {
+                    self.insert(name, AttributeValue::Number(number), false)
+                }
+                _ => self.add_attr(name, [values.to_string()]),
+            },
+            // Every other type is cast as string.
+            _ => self.insert(
+                name,
+                AttributeValue::from_strings(name, vec![values.to_string()]),
+                values.is_safe(),
+            ),
         }
     }
 
@@ -417,7 +744,7 @@ impl Attribute {
     /// assert!(attribute.has_attribute("class".into()));
     /// assert!(attribute.has_class("foo".into()));
     /// assert!(attribute.has_class("bar".into()));
-    /// assert_eq!(attribute.get_attr("data-id".into()), &vec!["123".to_string()]);
+    /// assert_eq!(attribute.get_attr("data-id".into()), vec!["123".to_string()]);
     ///
     /// let merge_attrs = Value::from_iter(vec![
     ///   ("class", Value::from_iter(vec!["alpha", "bar"])),
@@ -431,7 +758,7 @@ impl Attribute {
     /// assert!(attribute.has_class("bar".into()));
     /// assert!(attribute.has_class("alpha".into()));
     /// assert!(attribute.has_attribute("data-id".into()));
-    /// assert_eq!(attribute.get_attr("data-id".into()), &vec!["456".to_string()]);
+    /// assert_eq!(attribute.get_attr("data-id".into()), vec!["456".to_string()]);
     /// assert!(attribute.has_attribute("data-new".into()));
     /// ```
     pub fn merge_attrs_from_jinja(&mut self, values: &minijinja::value::Value) {
@@ -449,11 +776,9 @@ impl Attribute {
                             match val.kind() {
                                 ValueKind::String => {
                                     new_values.push(val.to_string());
-                                    let existing = self.attrs.get(str_key).unwrap();
+                                    let existing = self.get_attr(str_key);
                                     if existing.len() > 1 {
-                                        for value in self.attrs.get(str_key).unwrap().iter() {
-                                            new_values.push(value.to_string());
-                                        }
+                                        new_values.extend(existing);
                                     }
                                     self.add_attr(str_key, &new_values);
                                 }
@@ -463,9 +788,7 @@ impl Attribute {
                                             new_values.push(vv.to_string());
                                         }
                                     }
-                                    for value in self.attrs.get(str_key).unwrap().iter() {
-                                        new_values.push(value.to_string());
-                                    }
+                                    new_values.extend(self.get_attr(str_key));
                                     self.add_attr(str_key, &new_values);
                                 }
                                 ValueKind::Map => {
@@ -511,7 +834,123 @@ impl Attribute {
     /// ```
     ///
     pub fn remove_attr_by_name(&mut self, name: &str) {
+        // Nested data are removed with their expanded `data-*` attributes.
+        if !self.attrs.contains_key(name) && (name == "data" || name.starts_with("data-")) {
+            let prefix = format!("{name}-");
+            self.attrs.retain(|key, _| !key.starts_with(&prefix));
+            self.safe.retain(|key| !key.starts_with(&prefix));
+        }
         self.attrs.swap_remove(name);
+        self.safe.remove(name);
+    }
+
+    /// Set a `style` declaration, replacing the value of an existing property.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr("style", vec!["color: red; margin: 0"]);
+    /// attribute.set_style("COLOR", "blue");
+    /// attribute.set_style("--gap", "1rem");
+    ///
+    /// assert_eq!(attribute.to_string(), " style=\"color: blue; margin: 0; --gap: 1rem;\"");
+    /// ```
+    pub fn set_style(&mut self, property: &str, value: &str) {
+        let declaration = format!("{property}: {value}");
+        self.insert(
+            "style",
+            AttributeValue::from_strings("style", vec![declaration]),
+            false,
+        );
+    }
+
+    /// Remove a `style` declaration, the attribute is removed when empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr("style", vec!["color: red; margin: 0"]);
+    /// attribute.remove_style("color");
+    /// assert_eq!(attribute.to_string(), " style=\"margin: 0;\"");
+    ///
+    /// attribute.remove_style("margin");
+    /// assert_eq!(attribute.to_string(), "");
+    /// ```
+    pub fn remove_style(&mut self, property: &str) {
+        let property = match property.trim().starts_with("--") {
+            true => property.trim().to_string(),
+            false => property.trim().to_ascii_lowercase(),
+        };
+        if let Some(AttributeValue::Style(declarations)) = self.attrs.get_mut("style") {
+            declarations.retain(|(existing, _)| *existing != property);
+            if declarations.is_empty() {
+                self.remove_attr_by_name("style");
+            }
+        }
+    }
+
+    /// Set an ARIA attribute, the `aria-` prefix is optional. Booleans are
+    /// rendered as `"true"` or `"false"`, an undefined or none value removes
+    /// the attribute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    /// use minijinja::value::Value;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.set_aria("expanded", Value::from(false));
+    /// attribute.set_aria("aria-controls", Value::from("menu"));
+    /// assert_eq!(attribute.to_string(), " aria-expanded=\"false\" aria-controls=\"menu\"");
+    ///
+    /// attribute.set_aria("Expanded", Value::from(()));
+    /// assert_eq!(attribute.to_string(), " aria-controls=\"menu\"");
+    /// ```
+    pub fn set_aria(&mut self, name: &str, value: minijinja::value::Value) {
+        let name = aria_name(name);
+        if value.is_undefined() || value.is_none() {
+            self.remove_attr_by_name(&name);
+            return;
+        }
+        self.add_attr_from_jinja(&name, value);
+    }
+
+    /// Add ids to an id reference list as `aria-describedby`, each id once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr("aria-describedby", vec!["hint"]);
+    /// attribute.add_id_refs("aria-describedby", vec!["error hint", "help"]);
+    ///
+    /// assert_eq!(attribute.to_string(), " aria-describedby=\"hint error help\"");
+    /// ```
+    pub fn add_id_refs(&mut self, name: &str, ids: impl IntoIterator<Item = impl AsRef<str>>) {
+        let mut refs: Vec<String> = Vec::new();
+        let existing = self.get_attr(name);
+        let ids: Vec<String> = ids.into_iter().map(|id| id.as_ref().to_string()).collect();
+        for id in existing
+            .iter()
+            .chain(ids.iter())
+            .flat_map(|id| id.split_whitespace())
+        {
+            if !refs.iter().any(|existing| existing == id) {
+                refs.push(id.to_string());
+            }
+        }
+        if !refs.is_empty() {
+            self.add_value(name, AttributeValue::Tokens(refs));
+        }
     }
 
     /// Removes the attribute with the specified name from a minijinja::value::Value.
@@ -545,13 +984,14 @@ impl Attribute {
     /// assert!(attribute.has_class("other".into()));
     /// ```
     pub fn remove_class_by_name(&mut self, class: &str) {
-        let mut classes = match self.attrs.get_mut("class") {
-            Some(classes) => classes.clone(),
+        let mut classes = match self.attrs.get("class") {
+            Some(classes) => classes.strings(),
             None => return,
         };
 
         classes.retain(|c| *c != class);
-        self.attrs.insert("class".to_string(), classes);
+        self.attrs
+            .insert("class".to_string(), AttributeValue::Tokens(classes));
     }
 
     /// Check if the element has a specific class name under the 'class' attribute.
@@ -577,7 +1017,7 @@ impl Attribute {
     /// ```
     pub fn has_class(&self, class: minijinja::value::Value) -> bool {
         if let Some(classes) = self.attrs.get("class") {
-            if classes.contains(&class.to_string()) {
+            if classes.strings().contains(&class.to_string()) {
                 return true;
             }
         }
@@ -608,20 +1048,50 @@ impl Attribute {
     pub fn has_attribute(&self, name: minijinja::value::Value) -> bool {
         self.attrs.contains_key(&name.to_string())
     }
+
+    /// Get the rendered attributes as a map of names and values joined with a
+    /// space, bare attributes have an empty value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dilla_renderer::attribute::Attribute;
+    ///
+    /// let mut attribute = Attribute::new();
+    /// attribute.add_attr("class", vec!["foo", "bar"]);
+    /// attribute.add_attr("id", vec!["baz"]);
+    ///
+    /// let map = attribute.to_map();
+    /// assert_eq!(map["class"], "foo bar");
+    /// assert_eq!(map["id"], "baz");
+    /// ```
+    pub fn to_map(&self) -> IndexMap<String, String> {
+        self.attrs
+            .iter()
+            .filter_map(|(name, value)| {
+                html_value(name, value).map(|value| (name.clone(), value.unwrap_or_default()))
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for Attribute {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut output = String::new();
-        for (name, values) in &self.attrs {
-            let value = values.to_vec().join(" ");
-            // Do not populate right part of attribute unless it's class or style.
-            // @todo a list and a boolean check for value?
-            // @todo async and defer when true?
-            if value.is_empty() && name != "class" && name != "style" {
-                output.push_str(&format!("{} ", name));
-            } else {
-                output.push_str(&format!("{}=\"{}\" ", name, values.to_vec().join(" ")));
+        for (name, value) in &self.attrs {
+            if !is_valid_name(name) {
+                continue;
+            }
+            match html_value(name, value) {
+                None => continue,
+                Some(None) => output.push_str(&format!("{} ", name)),
+                Some(Some(value)) => {
+                    let value = match self.safe.contains(name) {
+                        true => value,
+                        false => escape(&value),
+                    };
+                    output.push_str(&format!("{}=\"{}\" ", name, value));
+                }
             }
         }
 
@@ -640,14 +1110,44 @@ impl minijinja::value::Object for Attribute {
         name: &str,
         args: &[minijinja::value::Value],
     ) -> Result<minijinja::value::Value, Error> {
+        // Methods changing the attributes return a new object, as the filters.
+        let mut new_attributes = self.clone();
         match name {
+            "addClass" => {
+                new_attributes.add_attr("class", args_to_strings(args));
+                Ok(minijinja::value::Value::from_object(new_attributes))
+            }
+            "removeClass" => {
+                for class in args_to_strings(args) {
+                    new_attributes.remove_class_by_name(&class);
+                }
+                Ok(minijinja::value::Value::from_object(new_attributes))
+            }
+            "setAttribute" => {
+                let (attribute, value): (&str, Option<minijinja::value::Value>) = from_args(args)?;
+                new_attributes.add_attr_from_jinja(attribute, value.unwrap_or_default());
+                Ok(minijinja::value::Value::from_object(new_attributes))
+            }
             "removeAttribute" => {
-                let mut new_attributes = self.clone();
-                let (attribute,): (&str,) = from_args(args)?;
-                new_attributes.remove_attr_by_name(attribute);
-
+                for attribute in args_to_strings(args) {
+                    new_attributes.remove_attr_by_name(&attribute);
+                }
                 Ok(minijinja::value::Value::from_object(new_attributes))
             }
+            "hasClass" => {
+                let (class,): (minijinja::value::Value,) = from_args(args)?;
+                Ok(self.has_class(class).into())
+            }
+            "hasAttribute" => {
+                let (attribute,): (minijinja::value::Value,) = from_args(args)?;
+                Ok(self.has_attribute(attribute).into())
+            }
+            "toArray" => {
+                let _: () = from_args(args)?;
+                Ok(minijinja::value::Value::from_serialize(
+                    serde_json::Value::from(new_attributes),
+                ))
+            }
             _ => Err(Error::new(
                 minijinja::ErrorKind::UnknownMethod,
                 format!("Attribute object has no method named {}", name),
@@ -661,7 +1161,7 @@ impl From<Attribute> for serde_json::Value {
         let json_map: serde_json::Map<String, serde_json::Value> = attribute
             .attrs
             .into_iter()
-            .map(|(key, values)| (key, serde_json::Value::from(values)))
+            .map(|(key, value)| (key, serde_json::Value::from(value)))
             .collect();
         serde_json::Value::Object(json_map)
     }
@@ -672,59 +1172,329 @@ impl From<Attribute> for minijinja::Value {
         attribute
             .attrs
             .into_iter()
-            .map(|(k, v)| (k, v.into_iter().map(Into::into).collect::<Vec<String>>()))
+            .map(|(k, v)| {
+                let to_value = |value: String| match attribute.safe.contains(&k) {
+                    true => minijinja::Value::from_safe_string(value),
+                    false => minijinja::Value::from(value),
+                };
+                let value = match v {
+                    AttributeValue::Bool(value) => minijinja::Value::from(value),
+                    AttributeValue::Number(value) => minijinja::Value::from_serialize(&value),
+                    AttributeValue::String(value) => to_value(value),
+                    AttributeValue::Tokens(values) => values.into_iter().map(to_value).collect(),
+                    value @ AttributeValue::Style(_) => {
+                        value.strings().into_iter().map(to_value).collect()
+                    }
+                };
+                (k, value)
+            })
             .collect()
     }
 }
 
 impl From<minijinja::Value> for Attribute {
     fn from(v: minijinja::Value) -> Self {
-        let mut attrs: IndexMap<String, Vec<String>> = IndexMap::new();
-        match v.kind() {
-            ValueKind::Map => {
-                if let Ok(iter) = v.try_iter() {
-                    for key in iter {
-                        if let Some(str_key) = key.as_str() {
-                            if let Ok(val) = v.get_item(&key) {
-                                if val.kind() == ValueKind::Seq {
-                                    let mut new_values: Vec<String> = Vec::new();
-                                    if let Ok(iter) = val.try_iter() {
-                                        for vv in iter {
-                                            // We can have some undefined or empty value here.
-                                            // @todo find source of undefined
-                                            if vv.to_string() != "undefined"
-                                                || vv.to_string().is_empty()
-                                            {
-                                                new_values.push(vv.to_string());
-                                            }
-                                        }
-                                    }
-                                    attrs.insert(str_key.to_string(), new_values);
-                                }
-                                // Every other type is cast as string to handle int, bool, float...
-                                // @todo add tests
-                                else {
-                                    attrs.insert(str_key.to_string(), vec![val.to_string()]);
-                                }
-                            }
-                        }
-                    }
+        if let Some(attribute) = v.downcast_object_ref::<Attribute>() {
+            return attribute.clone();
+        }
+
+        let mut attribute = Attribute::new();
+        if v.kind() == ValueKind::Map {
+            attribute.add_attrs_from_jinja(&v);
+        }
+
+        attribute
+    }
+}
+
+/// Build a value from strings.
+fn strings_value(name: &str, values: impl IntoIterator<Item = impl AsRef<str>>) -> AttributeValue {
+    let values = values
+        .into_iter()
+        .map(|value| value.as_ref().to_string())
+        .collect();
+    AttributeValue::from_strings(name, values)
+}
+
+/// Check values of an attribute are added to the existing ones.
+fn is_list(name: &str) -> bool {
+    name == "style" || TOKEN_LIST_ATTRIBUTES.contains(&name)
+}
+
+/// Get the HTML value of an attribute, `None` when omitted and `Some(None)`
+/// for a bare attribute.
+fn html_value(name: &str, value: &AttributeValue) -> Option<Option<String>> {
+    let text = value.to_string();
+    // Boolean attributes are only the name, or nothing when false.
+    if BOOLEAN_ATTRIBUTES.contains(&name) {
+        match text.as_str() {
+            "false" => return None,
+            "" | "true" => return Some(None),
+            text if text == name => return Some(None),
+            _ => (),
+        }
+    }
+    // Do not populate right part of attribute unless it's class or style.
+    if text.is_empty() && name != "class" && name != "style" {
+        return Some(None);
+    }
+
+    Some(Some(text))
+}
+
+/// Flatten method arguments of strings and lists of strings, as Twig
+/// `addClass('a', ['b', 'c'])`.
+fn args_to_strings(args: &[minijinja::value::Value]) -> Vec<String> {
+    let mut strings = Vec::new();
+    for arg in args {
+        match arg.kind() {
+            ValueKind::Seq => {
+                if let Ok(iter) = arg.try_iter() {
+                    strings.extend(iter.map(|value| value.to_string()));
                 }
             }
-            // Every other type is cast as string to handle int, bool, float...
-            _ => {
-                // @todo
+            ValueKind::Undefined | ValueKind::None => (),
+            _ => strings.push(arg.to_string()),
+        }
+    }
+    strings
+}
+
+/// Prefix an ARIA attribute name with `aria-`, lowercase.
+pub(crate) fn aria_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    match name.starts_with("aria-") {
+        true => name,
+        false => format!("aria-{name}"),
+    }
+}
+
+/// Split a style in declarations, ignoring `;` in quotes and parentheses.
+fn parse_style(style: &str) -> Vec<(String, String)> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut depth: usize = 0;
+    let mut start = 0;
+    for (position, char) in style.char_indices() {
+        match (quote, char) {
+            (Some(open), _) if char == open => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(char),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => {
+                parts.push(&style[start..position]);
+                start = position + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&style[start..]);
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.split_once(':') {
+            Some((property, value))
+                if !property.trim().is_empty()
+                    && !property.trim().contains(char::is_whitespace) =>
+            {
+                let property = property.trim();
+                let property = match property.starts_with("--") {
+                    true => property.to_string(),
+                    false => property.to_ascii_lowercase(),
+                };
+                (property, value.trim().to_string())
             }
+            _ => (String::new(), part.to_string()),
+        })
+        .collect()
+}
+
+/// Merge style declarations, the last value of a property wins.
+fn merge_declarations(declarations: &mut Vec<(String, String)>, new: Vec<(String, String)>) {
+    for (property, value) in new {
+        let existing = declarations
+            .iter_mut()
+            .find(|(existing, _)| !property.is_empty() && *existing == property);
+        match existing {
+            Some(existing) => existing.1 = value,
+            None => declarations.push((property, value)),
         }
+    }
+}
 
-        Attribute { attrs }
+/// Check an attribute name is valid HTML, without whitespace, quotes or
+/// characters closing the tag.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|char| {
+            char.is_whitespace()
+                || char.is_control()
+                || matches!(char, '"' | '\'' | '<' | '>' | '/' | '=' | '`')
+        })
+}
+
+/// Escape an attribute value for a double quoted attribute.
+fn escape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for char in value.chars() {
+        match char {
+            '&' => output.push_str("&amp;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            _ => output.push(char),
+        }
     }
+
+    output
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_boolean_attributes() {
+        let mut attribute = Attribute::new();
+        attribute.add_attr("src", vec!["foo.js"]);
+        attribute.add_attr("async", vec!["true"]);
+        attribute.add_attr("defer", vec!["false"]);
+        attribute.add_attr("nomodule", vec![""]);
+        attribute.add_attr("hidden", vec!["until-found"]);
+
+        assert_eq!(
+            attribute.to_string(),
+            " src=\"foo.js\" async nomodule hidden=\"until-found\""
+        );
+    }
+
+    #[test]
+    fn test_build_attributes_hostile() {
+        let mut attribute = Attribute::new();
+        attribute.build_attributes(&serde_json::json!({
+            "class": ["a\"b", "<c>"],
+            "title": "\" onmouseover=\"alert(1)",
+            "x onclick": "alert(1)",
+            "": "empty",
+            "data-a&b": "Tom & Jerry"
+        }));
+
+        assert_eq!(
+            attribute.to_string(),
+            " class=\"a&quot;b &lt;c&gt;\" data-a&b=\"Tom &amp; Jerry\" title=\"&quot; onmouseover=&quot;alert(1)\""
+        );
+    }
+
+    #[test]
+    fn test_safe_values_merge() {
+        let mut attribute = Attribute::new();
+        attribute.add_attr("class", vec!["a&b"]);
+        attribute.add_safe_attr("class", vec!["c&amp;d"]);
+        attribute.add_attr("class", vec!["<e>"]);
+
+        assert_eq!(
+            attribute.to_string(),
+            " class=\"a&amp;b c&amp;d &lt;e&gt;\""
+        );
+
+        // Replaced attributes are not safe anymore.
+        attribute.add_safe_attr("title", vec!["&lt;"]);
+        attribute.add_attr("title", vec!["<"]);
+        assert_eq!(attribute.get_attr("title"), &["<"]);
+        assert!(attribute.to_string().ends_with(" title=\"&lt;\""));
+    }
+
+    #[test]
+    fn test_safe_values_from_jinja() {
+        let values = minijinja::Value::from_iter(vec![
+            (
+                "title",
+                minijinja::Value::from_safe_string("&lt;b&gt;".into()),
+            ),
+            ("alt", minijinja::Value::from("<b>")),
+        ]);
+        let attribute: Attribute = values.into();
+        assert_eq!(
+            attribute.to_string(),
+            " alt=\"&lt;b&gt;\" title=\"&lt;b&gt;\""
+        );
+
+        let value: minijinja::Value = attribute.into();
+        let attribute: Attribute = value.into();
+        assert_eq!(
+            attribute.to_string(),
+            " alt=\"&lt;b&gt;\" title=\"&lt;b&gt;\""
+        );
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let mut attribute = Attribute::new();
+        attribute.add_attr_from_serde(&serde_json::json!({
+            "hidden": false,
+            "disabled": true,
+            "data-bool": false,
+            "rel": ["noopener", "external"],
+            "data": {"id": 5, "user": {"name": "Bob"}},
+            "tabindex": -1
+        }));
+
+        assert_eq!(
+            attribute.to_string(),
+            " data-id=\"5\" data-user-name=\"Bob\" data-bool=\"false\" disabled rel=\"noopener external\" tabindex=\"-1\""
+        );
+        assert_eq!(
+            serde_json::Value::from(attribute.clone()),
+            serde_json::json!({
+                "data-bool": false,
+                "data-id": 5,
+                "data-user-name": "Bob",
+                "disabled": true,
+                "hidden": false,
+                "rel": ["noopener", "external"],
+                "tabindex": -1
+            })
+        );
+        assert!(!attribute.to_map().contains_key("hidden"));
+        assert_eq!(attribute.to_map()["disabled"], "");
+
+        attribute.remove_attr_by_name("data-user");
+        assert!(!attribute.has_attribute("data-user-name".into()));
+        assert!(attribute.has_attribute("data-id".into()));
+    }
+
+    #[test]
+    fn test_style_declarations() {
+        assert_eq!(
+            parse_style("Color: red;; background: url('a;b.png'); --Gap: var(--x, 1rem); some"),
+            vec![
+                ("color".to_string(), "red".to_string()),
+                ("background".to_string(), "url('a;b.png')".to_string()),
+                ("--Gap".to_string(), "var(--x, 1rem)".to_string()),
+                (String::new(), "some".to_string()),
+            ]
+        );
+
+        let mut attribute = Attribute::new();
+        attribute.add_attr("style", vec!["color: red; margin: 0", "color: blue"]);
+        attribute.add_attr("style", vec!["padding: 1px;"]);
+        assert_eq!(
+            attribute.get_attr("style"),
+            vec!["color: blue;", "margin: 0;", "padding: 1px;"]
+        );
+
+        attribute.remove_style("Margin");
+        attribute.set_style("padding", "2px");
+        assert_eq!(
+            attribute.to_string(),
+            " style=\"color: blue; padding: 2px;\""
+        );
+    }
+
     #[test]
     fn test_has_class_present() {
         let mut attribute = Attribute::new();
@@ -763,9 +1533,9 @@ mod tests {
         let mut attribute = Attribute::new();
         attribute.add_attr("class", vec!["btn"]);
 
-        let expected_attrs: IndexMap<String, Vec<String>> = vec![(
+        let expected_attrs: IndexMap<String, AttributeValue> = vec![(
             "class".to_string(),
-            vec!["btn".to_string()].into_iter().collect(),
+            AttributeValue::Tokens(vec!["btn".to_string()]),
         )]
         .into_iter()
         .collect();
@@ -778,11 +1548,9 @@ mod tests {
         let mut attribute = Attribute::new();
         attribute.add_attr("class", vec!["btn", "active"]);
 
-        let expected_attrs: IndexMap<String, Vec<String>> = vec![(
+        let expected_attrs: IndexMap<String, AttributeValue> = vec![(
             "class".to_string(),
-            vec!["btn".to_string(), "active".to_string()]
-                .into_iter()
-                .collect(),
+            AttributeValue::Tokens(vec!["btn".to_string(), "active".to_string()]),
         )]
         .into_iter()
         .collect();
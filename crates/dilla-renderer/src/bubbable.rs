@@ -1,5 +1,8 @@
 //! Handle *Bubabble* properties as **@attached**, **@variables**, **@library**,
-//! and **@trans**.
+//! **@lang** and **@trans**.
+//!
+//! With render option `scoped_local_variables`, the scoped
+//! **@local_variables** are collected too to build a stylesheet.
 //!
 //! Bubabble properties are collected from anywhere in the payload and
 //! bubbled to the top. Duplicates are not checked and replaced when reading
@@ -8,14 +11,18 @@
 //! them.
 
 use crate::{
+    format::push_line,
+    locale,
     renderable::{Html, Renderable, KEY_COMPONENT, KEY_COMPONENT_VARIANT, SEP_COMPONENT_VARIANT},
-    DEFINITION, KEY_PREFIX,
+    scoped::{Scoped, KEY_LOCAL_VARIABLES},
+    timing, DEFINITION, KEY_PREFIX,
 };
 use indexmap::IndexMap;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 const KEY_ATTACHED: &str = "attached";
+const KEY_LANG: &str = "lang";
 const KEY_LIBRARY: &str = "library";
 const KEY_TRANS: &str = "trans";
 const KEY_VARIABLES: &str = "variables";
@@ -32,7 +39,13 @@ pub struct Bubbable {
     pub style: String,
     /// Collect translation to be used in templates with filter `|t`.
     pub translation: HashMap<String, String>,
+    /// Collect translation catalogs by locale, as `@trans: {"fr": {...}}`.
+    pub catalogs: HashMap<String, HashMap<String, String>>,
+    /// Locale of the payload from the first **@lang**.
+    pub lang: Option<String>,
     variables: HashMap<String, HashMap<String, Value>>,
+    /// Collect **@local_variables** declarations by scoped class name.
+    local_variables: IndexMap<String, Vec<String>>,
 }
 
 impl Bubbable {
@@ -43,7 +56,10 @@ impl Bubbable {
             library: Library::new(),
             style: String::new(),
             translation: HashMap::new(),
+            catalogs: HashMap::new(),
+            lang: None,
             variables: HashMap::new(),
+            local_variables: IndexMap::new(),
         }
     }
 
@@ -89,6 +105,21 @@ impl Bubbable {
         }
     }
 
+    /// Render `@local_variables` as class scoped rules and attach to the
+    /// 'style' field.
+    ///
+    /// Used with render option `scoped_local_variables`, where elements and
+    /// components get the class instead of an inline `style` attribute.
+    pub fn render_local_variables(&mut self) {
+        for (class, declarations) in self.local_variables.iter() {
+            self.style.push_str(&format!(".{} {{\n", class));
+            for declaration in declarations {
+                self.style.push_str(&format!("  {}\n", declaration));
+            }
+            self.style.push_str("}\n");
+        }
+    }
+
     /// Collect bubabble properties from a serde_json::Value.
     ///
     /// This recursively traverses the provided `data` value to find and collect any
@@ -106,11 +137,18 @@ impl Bubbable {
     /// * `data` - The serde_json `Value` to collect bubbable from.
     ///
     pub fn collect(&mut self, data: &Value) {
+        let _span = timing::span("collect");
+        self.collect_value(data);
+    }
+
+    fn collect_value(&mut self, data: &Value) {
         let key_component = KEY_PREFIX.to_string() + KEY_COMPONENT;
         let key_attached = KEY_PREFIX.to_string() + KEY_ATTACHED;
         let key_variables = KEY_PREFIX.to_string() + KEY_VARIABLES;
         let key_library = KEY_PREFIX.to_string() + KEY_LIBRARY;
         let key_trans = KEY_PREFIX.to_string() + KEY_TRANS;
+        let key_lang = KEY_PREFIX.to_string() + KEY_LANG;
+        let key_local_variables = KEY_PREFIX.to_string() + KEY_LOCAL_VARIABLES;
 
         match data {
             Value::Object(map) => {
@@ -121,13 +159,17 @@ impl Bubbable {
                         key if key == key_variables => self.handle_variables(value),
                         key if key == key_library => self.handle_library(value),
                         key if key == key_trans => self.handle_trans(value),
-                        _ => self.collect(value),
+                        key if key == key_lang => self.handle_lang(value),
+                        key if key == key_local_variables => {
+                            self.handle_local_variables(key, value)
+                        }
+                        _ => self.collect_value(value),
                     }
                 }
             }
             Value::Array(array) => {
                 for value in array.iter() {
-                    self.collect(value);
+                    self.collect_value(value);
                 }
             }
             _ => {}
@@ -173,14 +215,12 @@ impl Bubbable {
 
                 let mut renderable = Renderable::new(obj.as_object().unwrap().to_owned());
                 renderable.build();
-                self.attached_build.push_str(&renderable.to_html_string());
-                self.attached_build.push('\n');
+                push_line(&mut self.attached_build, &renderable.to_html_string());
             }
         } else if let Value::Object(obj) = value {
             let mut renderable = Renderable::new(obj.to_owned());
             renderable.build();
-            self.attached_build.push_str(&renderable.to_html_string());
-            self.attached_build.push('\n');
+            push_line(&mut self.attached_build, &renderable.to_html_string());
         }
     }
 
@@ -226,6 +266,13 @@ impl Bubbable {
                     {
                         self.add_library_js(js_url.to_owned(), attributes.to_owned());
                     }
+                } else if asset_type == "inline" {
+                    for (name, script) in asset_data.as_object().unwrap_or(&serde_json::Map::new())
+                    {
+                        if script.is_object() {
+                            self.library.add_inline(name.to_owned(), script.to_owned());
+                        }
+                    }
                 }
             }
         }
@@ -234,14 +281,53 @@ impl Bubbable {
     fn handle_trans(&mut self, value: &Value) {
         if let Value::Object(obj) = value {
             for (term, translation) in obj.iter() {
-                if !self.translation.contains_key(term) {
-                    self.translation
-                        .insert(term.to_owned(), translation.to_string());
+                // A Map is the catalog of a locale.
+                if let Value::Object(catalog) = translation {
+                    let translations = self.catalogs.entry(locale::normalize(term)).or_default();
+                    for (term, translation) in catalog.iter() {
+                        add_translation(translations, term, translation);
+                    }
+                    continue;
                 }
+                add_translation(&mut self.translation, term, translation);
+            }
+        }
+    }
+
+    fn handle_lang(&mut self, value: &Value) {
+        if let Value::String(lang) = value {
+            if self.lang.is_none() && !lang.trim().is_empty() {
+                self.lang = Some(locale::normalize(lang));
             }
         }
     }
 
+    /// Get the translation for a fallback chain of locales, the first locale
+    /// with a term wins over the next ones and the translation without locale.
+    pub fn translation_for(&self, chain: &[String]) -> HashMap<String, String> {
+        let mut translation = self.translation.clone();
+        for locale in chain.iter().rev() {
+            if let Some(catalog) = self.catalogs.get(locale) {
+                translation.extend(catalog.clone());
+            }
+        }
+        translation
+    }
+
+    fn handle_local_variables(&mut self, key: &str, value: &Value) {
+        let mut data = Map::new();
+        data.insert(key.to_owned(), value.to_owned());
+
+        let mut scoped = Scoped::new();
+        scoped.collect(&data);
+
+        if let Some(class) = scoped.local_variables_class() {
+            self.local_variables
+                .entry(class)
+                .or_insert(scoped.local_variables);
+        }
+    }
+
     fn build_library_dependencies(&mut self, name: &str) {
         if !DEFINITION.libraries_keys.contains(&name) {
             return;
@@ -364,6 +450,8 @@ impl Bubbable {
 pub struct Library {
     pub css: Vec<String>,
     pub js: IndexMap<String, Value>,
+    /// Inline scripts by name, with code and initialization options.
+    pub inline: IndexMap<String, Value>,
 }
 
 impl Library {
@@ -371,6 +459,13 @@ impl Library {
         Self {
             css: Vec::new(),
             js: IndexMap::new(),
+            inline: IndexMap::new(),
+        }
+    }
+
+    fn add_inline(&mut self, key: String, value: Value) {
+        if !self.inline.contains_key(&key) {
+            self.inline.insert(key, value);
         }
     }
 
@@ -388,3 +483,16 @@ impl Library {
         self.js.contains_key(key)
     }
 }
+
+/// Add a translation, the first one wins. Strings are kept as is, other
+/// values as json.
+fn add_translation(translations: &mut HashMap<String, String>, term: &str, translation: &Value) {
+    if translations.contains_key(term) {
+        return;
+    }
+    let translation = match translation {
+        Value::String(translation) => translation.to_owned(),
+        translation => translation.to_string(),
+    };
+    translations.insert(term.to_owned(), translation);
+}
@@ -0,0 +1,68 @@
+// Subresource Integrity of the Design System libraries, used by the build.
+//
+// Kept apart from the build script to be tested.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha2::{Digest, Sha384};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Hashes of the libraries and the ones without integrity.
+#[derive(Debug, Default)]
+pub struct Integrity {
+    // Hashes as `url => sha384-...`, declared ones included.
+    pub hashes: HashMap<&'static str, &'static str>,
+    // Local files hashed, to rebuild on change.
+    pub files: Vec<PathBuf>,
+    // Messages for each library without integrity.
+    pub missing: Vec<String>,
+}
+
+// Hash the local libraries without a declared integrity from the assets
+// folder. Remote libraries can not be hashed and must be declared, a remote
+// library without integrity or a missing local file is reported as missing.
+pub fn compute(
+    urls: &[&'static str],
+    declared: &HashMap<&'static str, &'static str>,
+    assets_path: &Path,
+) -> Integrity {
+    let mut integrity = Integrity {
+        hashes: declared.clone(),
+        ..Integrity::default()
+    };
+
+    for url in urls {
+        if integrity.hashes.contains_key(url) {
+            continue;
+        }
+        if is_remote(url) {
+            integrity
+                .missing
+                .push(format!("[SRI] Missing integrity for remote library: {url}"));
+            continue;
+        }
+        let file = url.split(['?', '#']).next().unwrap_or(url);
+        let file = assets_path.join(file.trim_start_matches('/'));
+        match fs::read(&file) {
+            Ok(content) => {
+                let hash = format!("sha384-{}", STANDARD.encode(Sha384::digest(content)));
+                // Config is made of static str, leak is fine for a build script.
+                integrity
+                    .hashes
+                    .insert(url, Box::leak(hash.into_boxed_str()));
+                integrity.files.push(file);
+            }
+            Err(error) => integrity.missing.push(format!(
+                "[SRI] Missing local library {url} in {}: {error}",
+                assets_path.display()
+            )),
+        }
+    }
+
+    integrity
+}
+
+pub fn is_remote(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")
+}
@@ -1,6 +1,6 @@
 #![allow(unused)]
 
-use crate::build_config;
+use crate::build_config_with_integrity;
 use crate::SystemConfig;
 use std::collections::HashMap;
 
@@ -88,6 +88,13 @@ pub fn config() {
 
     libraries_js.insert("test/test.dependency-payload", lib_js);
 
+    // Remote library can not be computed and must be declared.
+    let mut libraries_integrity: HashMap<&str, &str> = HashMap::new();
+    libraries_integrity.insert(
+        "https://cdn.dilla.io/test.remote.js",
+        "sha384-oqVuAfXRKap7fdgcCY5uykM6+R9GqQ8K/uxy9rx7HNQlGYl1kPzQho1wx4JwY8wC",
+    );
+
     let components_with_library: Vec<&str> = vec!["test_with_library", "other", "other.variant"];
 
     let dependencies: Vec<&str> = vec!["test/test.dependency"];
@@ -138,5 +145,5 @@ pub fn config() {
         themes,
     };
 
-    build_config(config);
+    build_config_with_integrity(config, libraries_integrity);
 }
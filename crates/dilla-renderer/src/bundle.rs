@@ -0,0 +1,202 @@
+//! Bundle the libraries of the render as inline assets.
+//!
+//! Used by the `full_inline` output to build a self-contained HTML page for
+//! offline export. Local CSS and JS files are read from the asset root set
+//! with the render option `asset_root` and inlined in `<style>` and
+//! `<script>`. Remote or missing files are kept as regular tags.
+
+use crate::{
+    format::push_line,
+    hints::{origin, tag_attribute},
+    renderable::{Html, Renderable, KEY_SCRIPT_PLACEMENT},
+};
+
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+/// Result of an asset read.
+enum Asset {
+    Content(String),
+    Duplicate,
+    Unavailable,
+}
+
+/// Collect inlined assets for `head` and end of `body`.
+#[derive(Debug, Default)]
+pub struct Bundle {
+    root: Option<PathBuf>,
+    nonce_attribute: String,
+    files: Vec<PathBuf>,
+    pub head: String,
+    pub body: String,
+}
+
+impl Bundle {
+    pub fn new(root: Option<&str>, nonce_attribute: &str) -> Self {
+        Self {
+            root: root.map(PathBuf::from),
+            nonce_attribute: nonce_attribute.to_string(),
+            ..Bundle::default()
+        }
+    }
+
+    /// Inline stylesheets from rendered `<link>` tags.
+    pub fn add_stylesheets(&mut self, html: &str) {
+        for tag in html.split("<link").skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default();
+            let href = tag_attribute(tag, "href").unwrap_or_default();
+
+            let media = match tag_attribute(tag, "media") {
+                Some(media) => format!(" media=\"{}\"", media),
+                None => String::new(),
+            };
+            match self.read(&href) {
+                Asset::Content(content) => push_line(
+                    &mut self.head,
+                    &format!(
+                        "<style{}{}>\n{}\n</style>",
+                        media,
+                        self.nonce_attribute,
+                        content.replace("</style", "<\\/style")
+                    ),
+                ),
+                Asset::Duplicate => (),
+                Asset::Unavailable => push_line(&mut self.head, &format!("<link{}>", tag)),
+            }
+        }
+    }
+
+    /// Read the content of local stylesheets from rendered `<link>` tags.
+    pub fn read_stylesheets(&mut self, html: &str) -> String {
+        let mut css = String::new();
+        for tag in html.split("<link").skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default();
+            let href = tag_attribute(tag, "href").unwrap_or_default();
+            if let Asset::Content(content) = self.read(&href) {
+                css.push_str(&format!("{}\n", content));
+            }
+        }
+
+        css
+    }
+
+    /// Inline scripts from a map of `url => {attributes}`.
+    ///
+    /// A `nomodule` fallback is not needed for offline export and is ignored
+    /// when the module script is inlined.
+    pub fn add_scripts(&mut self, scripts: &IndexMap<String, Value>) {
+        for (url, attributes) in scripts {
+            let script = match self.read(url) {
+                Asset::Content(content) => {
+                    let script_type = match attributes.get("type").and_then(Value::as_str) {
+                        Some(script_type) => format!(" type=\"{}\"", script_type),
+                        None => String::new(),
+                    };
+                    format!(
+                        "<script{}{}>\n{}\n</script>",
+                        script_type,
+                        self.nonce_attribute,
+                        content.replace("</script", "<\\/script")
+                    )
+                }
+                Asset::Duplicate => continue,
+                Asset::Unavailable => Renderable::script(url, attributes).to_html_string(),
+            };
+            self.push_script(&script, attributes);
+        }
+    }
+
+    /// Add inline scripts from a map of `name => {script, options}`.
+    pub fn add_inline_scripts(&mut self, scripts: &IndexMap<String, Value>) {
+        for attributes in scripts.values() {
+            let script = Renderable::inline_script(attributes).to_html_string();
+            self.push_script(&script, attributes);
+        }
+    }
+
+    fn push_script(&mut self, script: &str, attributes: &Value) {
+        match attributes.get(KEY_SCRIPT_PLACEMENT).and_then(Value::as_str) == Some("head") {
+            true => push_line(&mut self.head, script),
+            false => push_line(&mut self.body, script),
+        }
+    }
+
+    /// Read a local asset once, remote or invalid path are unavailable.
+    fn read(&mut self, url: &str) -> Asset {
+        let Some(root) = &self.root else {
+            return Asset::Unavailable;
+        };
+        if url.is_empty() || origin(url).is_some() {
+            return Asset::Unavailable;
+        }
+
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let path = Path::new(path.trim_start_matches('/'));
+        // Only keep plain components to stay inside the asset root.
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Asset::Unavailable;
+        }
+
+        let file = root.join(path);
+        if self.files.contains(&file) {
+            return Asset::Duplicate;
+        }
+
+        match std::fs::read_to_string(&file) {
+            Ok(content) => {
+                self.files.push(file);
+                Asset::Content(content)
+            }
+            Err(_) => Asset::Unavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn root() -> String {
+        format!("{}/tests/bundle", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn test_inline_stylesheets() {
+        let mut bundle = Bundle::new(Some(&root()), "");
+        bundle.add_stylesheets(
+            r#"<link rel="stylesheet" href="/css/base.css" media="screen">
+<link rel="stylesheet" href="./css/base.css?v=2">
+<link rel="stylesheet" href="https://cdn.dilla.io/remote.css">
+<link rel="stylesheet" href="../secret.css">"#,
+        );
+
+        assert_eq!(
+            bundle.head,
+            "<style media=\"screen\">\nbody { color: #222; }\n\n</style>\n<link rel=\"stylesheet\" href=\"https://cdn.dilla.io/remote.css\">\n<link rel=\"stylesheet\" href=\"../secret.css\">"
+        );
+    }
+
+    #[test]
+    fn test_inline_scripts() {
+        let mut scripts = IndexMap::new();
+        scripts.insert(
+            "js/app.js".to_string(),
+            json!({"type": "module", "placement": "head"}),
+        );
+        scripts.insert("missing.js".to_string(), json!({"defer": "true"}));
+
+        let mut bundle = Bundle::new(Some(&root()), " nonce=\"abc\"");
+        bundle.add_scripts(&scripts);
+
+        assert_eq!(
+            bundle.head,
+            "<script type=\"module\" nonce=\"abc\">\nconsole.log(\"<\\/script>\");\n\n</script>"
+        );
+        assert_eq!(bundle.body, "<script src=\"missing.js\" defer></script>");
+    }
+}
@@ -0,0 +1,357 @@
+//! Build a DOM tree of the render for the `dom` output.
+//!
+//! Elements are built as nodes while rendering, components and templates are
+//! rendered by MiniJinja as HTML and their output is parsed in nodes. Headless
+//! front-ends can consume the structure without an HTML parser.
+
+use crate::renderable::VOID_ELEMENTS;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+/// Elements with raw text content, not parsed.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// A node of the DOM tree.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Node {
+    Element {
+        tag: String,
+        #[serde(skip_serializing_if = "IndexMap::is_empty")]
+        attributes: IndexMap<String, String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        children: Vec<Node>,
+    },
+    Text {
+        text: String,
+    },
+    Comment {
+        text: String,
+    },
+}
+
+impl Node {
+    /// Create a text node, `None` for whitespace only text.
+    pub fn text(text: &str) -> Option<Self> {
+        let text = text.trim();
+        match text.is_empty() {
+            true => None,
+            false => Some(Node::Text {
+                text: text.to_string(),
+            }),
+        }
+    }
+}
+
+/// Parse an HTML fragment in a list of nodes.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::dom::{parse, Node};
+///
+/// let nodes = parse(r#"<p class="a">Hello &amp; <b>bye</b></p><br>"#);
+///
+/// assert_eq!(nodes.len(), 2);
+/// if let Node::Element { tag, attributes, children } = &nodes[0] {
+///     assert_eq!(tag, "p");
+///     assert_eq!(attributes["class"], "a");
+///     assert_eq!(children[0], Node::Text { text: "Hello &".to_string() });
+/// }
+/// ```
+pub fn parse(html: &str) -> Vec<Node> {
+    // Stack of open elements, the root is the fragment itself.
+    let mut stack: Vec<(String, IndexMap<String, String>, Vec<Node>)> =
+        vec![(String::new(), IndexMap::new(), Vec::new())];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let start = rest.find('<').unwrap_or(rest.len());
+        push_node(&mut stack, Node::text(&unescape(&rest[..start])));
+        rest = &rest[start..];
+        if rest.is_empty() {
+            break;
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").unwrap_or(comment.len());
+            push_node(
+                &mut stack,
+                Some(Node::Comment {
+                    text: comment[..end].trim().to_string(),
+                }),
+            );
+            rest = comment.get(end + 3..).unwrap_or_default();
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing.find('>').map_or(closing.len(), |end| end + 1);
+            let name = closing[..end].trim_end_matches('>').trim().to_lowercase();
+            if let Some(position) = stack.iter().rposition(|(tag, _, _)| *tag == name) {
+                // Close every element up to the matching one.
+                while stack.len() > position.max(1) {
+                    close_element(&mut stack);
+                }
+            }
+            rest = &closing[end..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // Doctype and processing instructions are ignored.
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            rest = &rest[end..];
+        } else if let Some(tag) = rest[1..]
+            .starts_with(|c: char| c.is_ascii_alphabetic())
+            .then(|| StartTag::parse(rest))
+            .flatten()
+        {
+            rest = &rest[tag.length..];
+            let attributes: IndexMap<String, String> = tag
+                .attributes
+                .iter()
+                .map(|(name, value)| (name.clone(), unescape(value.as_deref().unwrap_or_default())))
+                .collect();
+
+            if RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) && !tag.self_closing {
+                let closing = format!("</{}", tag.name);
+                let end = rest.find(&closing).unwrap_or(rest.len());
+                let children = Node::text(&rest[..end]).into_iter().collect();
+                push_node(
+                    &mut stack,
+                    Some(Node::Element {
+                        tag: tag.name,
+                        attributes,
+                        children,
+                    }),
+                );
+                rest = &rest[end..];
+                rest = &rest[rest.find('>').map_or(rest.len(), |end| end + 1)..];
+            } else if tag.self_closing || VOID_ELEMENTS.contains(&tag.name.as_str()) {
+                push_node(
+                    &mut stack,
+                    Some(Node::Element {
+                        tag: tag.name,
+                        attributes,
+                        children: Vec::new(),
+                    }),
+                );
+            } else {
+                stack.push((tag.name, attributes, Vec::new()));
+            }
+        } else {
+            push_node(&mut stack, Node::text("<"));
+            rest = &rest[1..];
+        }
+    }
+
+    while stack.len() > 1 {
+        close_element(&mut stack);
+    }
+
+    stack
+        .pop()
+        .map(|(_, _, children)| children)
+        .unwrap_or_default()
+}
+
+fn push_node(stack: &mut [(String, IndexMap<String, String>, Vec<Node>)], node: Option<Node>) {
+    if let (Some(node), Some((_, _, children))) = (node, stack.last_mut()) {
+        children.push(node);
+    }
+}
+
+fn close_element(stack: &mut Vec<(String, IndexMap<String, String>, Vec<Node>)>) {
+    if let Some((tag, attributes, children)) = stack.pop() {
+        push_node(
+            stack,
+            Some(Node::Element {
+                tag,
+                attributes,
+                children,
+            }),
+        );
+    }
+}
+
+/// Decode the HTML entities produced by the escape.
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&#x2f;", "/")
+        .replace("&amp;", "&")
+}
+
+/// An HTML start tag with attributes.
+#[derive(Debug)]
+pub(crate) struct StartTag {
+    pub name: String,
+    pub attributes: Vec<(String, Option<String>)>,
+    pub self_closing: bool,
+    /// Length of the tag in the source HTML.
+    pub length: usize,
+}
+
+impl StartTag {
+    /// Parse a start tag at the beginning of the HTML.
+    pub fn parse(html: &str) -> Option<Self> {
+        let name_end = html[1..].find(|c: char| c.is_whitespace() || c == '>' || c == '/')? + 1;
+        let mut tag = StartTag {
+            name: html[1..name_end].to_lowercase(),
+            attributes: Vec::new(),
+            self_closing: false,
+            length: 0,
+        };
+
+        let mut position = name_end;
+        loop {
+            let rest = &html[position..];
+            let trimmed = rest.trim_start();
+            position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with('>') {
+                tag.length = position + 1;
+                return Some(tag);
+            }
+            if trimmed.starts_with("/>") {
+                tag.self_closing = true;
+                tag.length = position + 2;
+                return Some(tag);
+            }
+
+            let name_length =
+                trimmed.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))?;
+            if name_length == 0 {
+                // Stray character as a single `/`.
+                position += 1;
+                continue;
+            }
+            let name = trimmed[..name_length].to_lowercase();
+            position += name_length;
+
+            let value = if html[position..].starts_with('=') {
+                position += 1;
+                let rest = &html[position..];
+                match rest.chars().next()? {
+                    quote @ ('"' | '\'') => {
+                        let end = rest[1..].find(quote)? + 1;
+                        position += end + 1;
+                        Some(rest[1..end].to_string())
+                    }
+                    _ => {
+                        let end = rest
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(rest.len());
+                        position += end;
+                        Some(rest[..end].to_string())
+                    }
+                }
+            } else {
+                None
+            };
+            tag.attributes.push((name, value));
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_deref().unwrap_or_default())
+    }
+
+    /// Set or remove an attribute with a `None` value.
+    pub fn set_attribute(&mut self, name: &str, value: Option<String>) {
+        let position = self.attributes.iter().position(|(n, _)| n == name);
+        match (position, value) {
+            (Some(position), Some(value)) => self.attributes[position].1 = Some(value),
+            (Some(position), None) => {
+                self.attributes.remove(position);
+            }
+            (None, Some(value)) => self.attributes.push((name.to_string(), Some(value))),
+            (None, None) => (),
+        }
+    }
+}
+
+impl std::fmt::Display for StartTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (name, value) in &self.attributes {
+            match value {
+                Some(value) if value.contains('"') => write!(f, " {}='{}'", name, value)?,
+                Some(value) => write!(f, " {}=\"{}\"", name, value)?,
+                None => write!(f, " {}", name)?,
+            }
+        }
+        match self.self_closing {
+            true => write!(f, " />"),
+            false => write!(f, ">"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested() {
+        let nodes = parse(
+            "<!DOCTYPE html><div id=\"a\" hidden>\n<ul><li>1</li><li>2</ul><!-- note --><script>if (a < b) {}</script></div>",
+        );
+
+        let expected = vec![Node::Element {
+            tag: "div".to_string(),
+            attributes: IndexMap::from([
+                ("id".to_string(), "a".to_string()),
+                ("hidden".to_string(), "".to_string()),
+            ]),
+            children: vec![
+                Node::Element {
+                    tag: "ul".to_string(),
+                    attributes: IndexMap::new(),
+                    children: vec![
+                        Node::Element {
+                            tag: "li".to_string(),
+                            attributes: IndexMap::new(),
+                            children: vec![Node::Text {
+                                text: "1".to_string(),
+                            }],
+                        },
+                        Node::Element {
+                            tag: "li".to_string(),
+                            attributes: IndexMap::new(),
+                            children: vec![Node::Text {
+                                text: "2".to_string(),
+                            }],
+                        },
+                    ],
+                },
+                Node::Comment {
+                    text: "note".to_string(),
+                },
+                Node::Element {
+                    tag: "script".to_string(),
+                    attributes: IndexMap::new(),
+                    children: vec![Node::Text {
+                        text: "if (a < b) {}".to_string(),
+                    }],
+                },
+            ],
+        }];
+        assert_eq!(nodes, expected);
+    }
+
+    #[test]
+    fn test_node_json() {
+        let nodes = parse("<img src=\"a.png\" />text");
+        assert_eq!(
+            serde_json::to_string(&nodes).unwrap(),
+            r#"[{"type":"element","tag":"img","attributes":{"src":"a.png"}},{"type":"text","text":"text"}]"#
+        );
+    }
+}
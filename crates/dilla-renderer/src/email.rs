@@ -0,0 +1,489 @@
+//! Build an email-safe HTML document for the `email` output.
+//!
+//! Email clients ignore CSS custom properties, `<link>` stylesheets and
+//! scripts. Custom properties from the design system, **@variables** and
+//! **@local_variables** are resolved to concrete values, rules with simple
+//! selectors (`tag`, `.class`, `#id` and compounds) are inlined in `style`
+//! attributes and scripts are dropped. Rules that can not be inlined are kept
+//! in a `<style>` in the head.
+
+use crate::{dom::StartTag, renderable::VOID_ELEMENTS, DEFINITION};
+
+use indexmap::IndexMap;
+
+/// Maximum depth of nested `var()` resolution, avoid cycles.
+const MAX_VAR_DEPTH: usize = 8;
+/// Link relations not useful in an email.
+const DROPPED_LINK_REL: &[&str] = &["stylesheet", "preload", "modulepreload", "preconnect"];
+
+type Declarations = Vec<(String, String)>;
+type Variables = IndexMap<String, String>;
+
+/// A simple compound selector, as `div.foo#bar`.
+#[derive(Debug, Default)]
+struct Selector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Selector {
+    /// Parse a simple selector, `None` if it contains combinators, pseudo or
+    /// attribute selectors.
+    fn parse(selector: &str) -> Option<Self> {
+        let selector = selector.trim();
+        if selector.is_empty()
+            || !selector
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '#' | '*'))
+        {
+            return None;
+        }
+
+        let mut parsed = Selector::default();
+        let mut kind = ' ';
+        let mut name = String::new();
+        for c in selector.chars().chain(std::iter::once('.')) {
+            if c == '.' || c == '#' {
+                match kind {
+                    '.' if !name.is_empty() => parsed.classes.push(name.clone()),
+                    '#' if !name.is_empty() => parsed.id = Some(name.clone()),
+                    ' ' if !name.is_empty() && name != "*" => {
+                        parsed.tag = Some(name.to_lowercase())
+                    }
+                    ' ' => (),
+                    _ => return None,
+                }
+                kind = c;
+                name.clear();
+            } else {
+                name.push(c);
+            }
+        }
+
+        Some(parsed)
+    }
+
+    fn specificity(&self) -> (usize, usize, usize) {
+        (
+            self.id.is_some() as usize,
+            self.classes.len(),
+            self.tag.is_some() as usize,
+        )
+    }
+
+    fn matches(&self, tag: &str, id: Option<&str>, classes: &[&str]) -> bool {
+        !matches!(&self.tag, Some(t) if t != tag)
+            && !matches!(&self.id, Some(i) if Some(i.as_str()) != id)
+            && self.classes.iter().all(|c| classes.contains(&c.as_str()))
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    selector: Selector,
+    declarations: Declarations,
+}
+
+/// Inline CSS in HTML for email clients.
+#[derive(Debug, Default)]
+pub struct EmailInliner {
+    rules: Vec<Rule>,
+    root_variables: Variables,
+    unsupported: String,
+}
+
+impl EmailInliner {
+    /// Create the inliner from the CSS of the render, default values of the
+    /// custom properties are the design system variables.
+    pub fn new(css: &str) -> Self {
+        let mut inliner = EmailInliner::default();
+        for (name, value) in DEFINITION.variables.entries() {
+            inliner
+                .root_variables
+                .insert(format!("--{}", name), value.to_string());
+        }
+        inliner.parse(css);
+
+        inliner
+    }
+
+    /// CSS rules that can not be inlined, with custom properties resolved.
+    pub fn unsupported_css(&self) -> String {
+        resolve_variables(&self.unsupported, &self.root_variables, 0)
+    }
+
+    /// Inline the styles in the HTML and drop scripts and stylesheets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::email::EmailInliner;
+    ///
+    /// let inliner = EmailInliner::new(".btn { color: var(--main, red); padding: 4px; }");
+    /// let html = r#"<a class="btn" style="--main: blue; padding: 2px;">Go</a><script>x()</script>"#;
+    ///
+    /// assert_eq!(
+    ///     inliner.inline(html),
+    ///     r#"<a class="btn" style="color: blue; padding: 2px;">Go</a>"#
+    /// );
+    /// ```
+    pub fn inline(&self, html: &str) -> String {
+        let mut output = String::new();
+        // Open elements with their custom properties for inheritance.
+        let mut stack: Vec<(String, Variables)> = Vec::new();
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with("<!--") {
+                let end = rest.find("-->").map_or(rest.len(), |end| end + 3);
+                output.push_str(&rest[..end]);
+                rest = &rest[end..];
+            } else if let Some(closing) = rest.strip_prefix("</") {
+                let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+                let name = closing
+                    .split(|c: char| c == '>' || c.is_whitespace())
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                if let Some(position) = stack.iter().rposition(|(tag, _)| *tag == name) {
+                    stack.truncate(position);
+                }
+                output.push_str(&rest[..end]);
+                rest = &rest[end..];
+            } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+                let Some(tag) = StartTag::parse(rest) else {
+                    output.push_str(rest);
+                    break;
+                };
+                rest = &rest[tag.length..];
+
+                if tag.name == "script" {
+                    let end = rest.find("</script>").map_or(rest.len(), |end| end + 9);
+                    rest = &rest[end..];
+                    continue;
+                }
+                if tag.name == "link"
+                    && tag.attribute("rel").is_some_and(|rel| {
+                        rel.split_whitespace()
+                            .any(|rel| DROPPED_LINK_REL.contains(&rel))
+                    })
+                {
+                    continue;
+                }
+
+                let parent = stack
+                    .last()
+                    .map_or(&self.root_variables, |(_, variables)| variables);
+                let (tag, variables) = self.inline_tag(tag, parent);
+                output.push_str(&tag.to_string());
+
+                if !tag.self_closing && !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+                    stack.push((tag.name, variables));
+                }
+            } else {
+                output.push('<');
+                rest = &rest[1..];
+            }
+        }
+        output.push_str(rest);
+
+        output
+    }
+
+    /// Set the style attribute of a tag from the matching rules.
+    fn inline_tag(&self, mut tag: StartTag, parent: &Variables) -> (StartTag, Variables) {
+        let id = tag.attribute("id").map(str::to_string);
+        let class = tag.attribute("class").unwrap_or_default().to_string();
+        let classes: Vec<&str> = class.split_whitespace().collect();
+
+        let mut rules: Vec<(usize, &Rule)> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.selector.matches(&tag.name, id.as_deref(), &classes))
+            .collect();
+        rules.sort_by_key(|(order, rule)| (rule.selector.specificity(), *order));
+
+        let mut declarations: Declarations = rules
+            .into_iter()
+            .flat_map(|(_, rule)| rule.declarations.clone())
+            .collect();
+        // Inline style always win.
+        declarations.extend(parse_declarations(
+            tag.attribute("style").unwrap_or_default(),
+        ));
+
+        let mut variables = parent.clone();
+        let mut properties: IndexMap<String, String> = IndexMap::new();
+        for (name, value) in declarations {
+            if name.starts_with("--") {
+                variables.insert(name, value);
+            } else {
+                properties.insert(name, value);
+            }
+        }
+
+        let style = properties
+            .iter()
+            .map(|(name, value)| {
+                let value = resolve_variables(value, &variables, 0).replace('"', "'");
+                format!("{}: {};", name, value)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        tag.set_attribute("style", (!style.is_empty()).then_some(style));
+
+        (tag, variables)
+    }
+
+    fn parse(&mut self, css: &str) {
+        let css = strip_comments(css);
+        let mut rest = css.as_str();
+
+        while let Some(open) = rest.find(['{', ';']) {
+            let prelude = rest[..open].trim();
+
+            // Statement at-rule as @import or @charset.
+            if rest.as_bytes()[open] == b';' {
+                if prelude.starts_with('@') {
+                    self.unsupported.push_str(&format!("{};\n", prelude));
+                }
+                rest = &rest[open + 1..];
+                continue;
+            }
+
+            let end = block_end(rest, open);
+            let block = &rest[open + 1..end.saturating_sub(1).max(open + 1)];
+            if prelude.starts_with('@') {
+                self.unsupported
+                    .push_str(&format!("{}\n", &rest[..end].trim()));
+            } else {
+                self.add_rules(prelude, block);
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    fn add_rules(&mut self, selectors: &str, block: &str) {
+        let declarations = parse_declarations(block);
+
+        for selector in selectors.split(',') {
+            let selector = selector.trim();
+            if matches!(selector, ":root" | "html" | "body") {
+                for (name, value) in declarations.iter() {
+                    if name.starts_with("--") {
+                        self.root_variables.insert(name.clone(), value.clone());
+                    }
+                }
+                let properties: Vec<String> = declarations
+                    .iter()
+                    .filter(|(name, _)| !name.starts_with("--"))
+                    .map(|(name, value)| format!("{}: {};", name, value))
+                    .collect();
+                if !properties.is_empty() {
+                    self.unsupported.push_str(&format!(
+                        "{} {{ {} }}\n",
+                        selector,
+                        properties.join(" ")
+                    ));
+                }
+                continue;
+            }
+
+            match Selector::parse(selector) {
+                Some(selector) => self.rules.push(Rule {
+                    selector,
+                    declarations: declarations.clone(),
+                }),
+                None => {
+                    self.unsupported
+                        .push_str(&format!("{} {{ {} }}\n", selector, block.trim()))
+                }
+            }
+        }
+    }
+}
+
+/// Parse CSS declarations as `name: value;`.
+fn parse_declarations(block: &str) -> Declarations {
+    let mut declarations = Vec::new();
+    let mut level = 0;
+    let mut start = 0;
+
+    for (index, c) in block
+        .char_indices()
+        .chain(std::iter::once((block.len(), ';')))
+    {
+        match c {
+            '(' => level += 1,
+            ')' => level -= 1,
+            ';' if level <= 0 => {
+                if let Some((name, value)) = block[start..index].split_once(':') {
+                    let name = name.trim();
+                    let value = value.trim().trim_end_matches("!important").trim();
+                    if !name.is_empty() && !value.is_empty() {
+                        let name = match name.starts_with("--") {
+                            true => name.to_string(),
+                            false => name.to_lowercase(),
+                        };
+                        declarations.push((name, value.to_string()));
+                    }
+                }
+                start = (index + 1).min(block.len());
+            }
+            _ => (),
+        }
+    }
+
+    declarations
+}
+
+/// Replace `var(--name, fallback)` by the variable value.
+fn resolve_variables(value: &str, variables: &Variables, depth: usize) -> String {
+    if depth > MAX_VAR_DEPTH || !value.contains("var(") {
+        return value.to_string();
+    }
+
+    let mut output = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("var(") {
+        output.push_str(&rest[..start]);
+        let inner = &rest[start + 4..];
+
+        let mut level = 1;
+        let Some(end) = inner.find(|c: char| {
+            match c {
+                '(' => level += 1,
+                ')' => level -= 1,
+                _ => (),
+            }
+            level == 0
+        }) else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+
+        let (name, fallback) = match inner[..end].split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (inner[..end].trim(), None),
+        };
+        let replacement = variables
+            .get(name)
+            .map(String::as_str)
+            .or(fallback)
+            .map(|value| resolve_variables(value, variables, depth + 1))
+            .unwrap_or_default();
+        output.push_str(&replacement);
+        rest = &inner[end + 1..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut output = String::new();
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + end + 4..]);
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Position after the block closing brace matching the brace at `open`.
+fn block_end(css: &str, open: usize) -> usize {
+    let mut level = 0;
+    for (index, c) in css[open..].char_indices() {
+        match c {
+            '{' => level += 1,
+            '}' => {
+                level -= 1;
+                if level == 0 {
+                    return open + index + 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    css.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_parse() {
+        let selector = Selector::parse("a.btn.primary#go").unwrap();
+        assert_eq!(selector.tag, Some("a".to_string()));
+        assert_eq!(selector.id, Some("go".to_string()));
+        assert_eq!(selector.classes, vec!["btn", "primary"]);
+        assert_eq!(selector.specificity(), (1, 2, 1));
+
+        assert!(Selector::parse("div > p").is_none());
+        assert!(Selector::parse("a:hover").is_none());
+        assert!(Selector::parse("*").is_some());
+    }
+
+    #[test]
+    fn test_resolve_variables() {
+        let mut variables = Variables::new();
+        variables.insert("--a".to_string(), "var(--b)".to_string());
+        variables.insert("--b".to_string(), "#222".to_string());
+
+        assert_eq!(
+            resolve_variables("1px solid var(--a)", &variables, 0),
+            "1px solid #222"
+        );
+        assert_eq!(
+            resolve_variables("var(--c, var(--b))", &variables, 0),
+            "#222"
+        );
+        assert_eq!(resolve_variables("var(--c)", &variables, 0), "");
+    }
+
+    #[test]
+    fn test_inline_specificity_and_inheritance() {
+        let css = r#"
+            /* Comment */
+            :root { --text: #111; }
+            p { color: red; margin: 0; }
+            .note { color: var(--text); }
+            .box { --text: #333; }
+            .box p:first-child { font-weight: bold; }
+            @media (max-width: 600px) { p { margin: 4px; } }
+        "#;
+        let inliner = EmailInliner::new(css);
+        let html = r#"<div class="box"><p class="note">A</p><br></div><p class="note">B</p>"#;
+
+        assert_eq!(
+            inliner.inline(html),
+            r#"<div class="box"><p class="note" style="color: #333; margin: 0;">A</p><br></div><p class="note" style="color: #111; margin: 0;">B</p>"#
+        );
+        assert_eq!(
+            inliner.unsupported_css(),
+            ".box p:first-child { font-weight: bold; }\n@media (max-width: 600px) { p { margin: 4px; } }\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_drop_assets() {
+        let inliner = EmailInliner::new("");
+        let html = r#"<meta name="x" /><link rel="stylesheet" href="a.css"><link rel="icon" href="i.png"><script src="a.js" async></script><!-- <b> -->"#;
+
+        assert_eq!(
+            inliner.inline(html),
+            r#"<meta name="x" /><link rel="icon" href="i.png"><!-- <b> -->"#
+        );
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! Include all Dilla filter functions and abstractions.
 
-use crate::attribute::Attribute;
+use crate::attribute::{aria_name, Attribute};
+use crate::locale::{KEY_LOCALE, KEY_TRANSLATION};
+use crate::message;
 use crate::renderable::*;
 use crate::renderer::Renderer;
 
@@ -11,9 +13,31 @@ use minijinja::{
 };
 
 #[cfg(feature = "random")]
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 
+use serde::Serialize;
 use serde_json::Map;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    /// Last number of the ids generated by `unique_id` for each prefix.
+    static UNIQUE_IDS: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+}
+
+thread_local! {
+    /// Terms of `|t` without translation, collected during the render.
+    static MISSING_TRANSLATIONS: RefCell<Vec<MissingTranslation>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(feature = "random")]
+thread_local! {
+    /// Generator of `random()` shared by the whole render, nested included.
+    static RANDOM: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+// Names of the embedded templates, minijinja can not list them.
+include!(concat!(env!("OUT_DIR"), "/templates_names.rs"));
 
 /// Initializes a Jinja environment with various filters, functions, globals,
 /// and sets a formatter to render Maps in templates.
@@ -47,9 +71,16 @@ pub(crate) fn init_jinja_environnement() -> Environment<'static> {
     env.add_filter("has_class", has_class);
     env.add_filter("remove_class", remove_class);
     env.add_filter("remove_attribute", remove_attribute);
+    env.add_filter("set_style", set_style);
+    env.add_filter("remove_style", remove_style);
     env.add_filter("merge", merge);
+    env.add_filter("unique_id", unique_id);
+    env.add_filter("aria", aria);
+    env.add_filter("describedby", describedby);
+    env.add_filter("labelledby", labelledby);
 
     env.add_function("create_attribute", create_attribute);
+    env.add_function("unique_id", unique_id);
 
     env.add_global(
         "random",
@@ -69,6 +100,9 @@ pub(crate) fn init_jinja_environnement() -> Environment<'static> {
 /// Renders a value in an engine-specific format for Minijinja templates.
 /// This formatter is used by the init_jinja_environnement() function.
 ///
+/// Renderables and attributes are printed as markup, strings are escaped
+/// unless they are safe strings, as the output of `|safe` or a `set` block.
+///
 /// # Arguments
 ///
 /// * out - A mutable reference to the output where the formatted value will be written.
@@ -87,7 +121,7 @@ fn engine_formatter(
     // A Map is an object for Minijinja.
     if value.kind() == ValueKind::Map {
         if is_renderable(value) {
-            let output: String = render_value(state, value);
+            let output: minijinja::Value = render_value(state, value);
             return write!(out, "{}", output).map_err(Error::from);
         }
         // If not renderable then it's probably an attribute.
@@ -104,17 +138,11 @@ fn engine_formatter(
         }
     // If we have an array (Seq for Minijinja).
     } else if value.kind() == ValueKind::Seq {
-        let output: String = render_value(state, value);
+        let output: minijinja::Value = render_value(state, value);
         return write!(out, "{output}").map_err(Error::from);
-    // Approximately check for already rendered attributes in templates to avoid escape.
-    } else if value.kind() == ValueKind::String {
-        let test: &str = value.as_str().unwrap();
-        if test.contains("class=") || test.contains("id=") {
-            return write!(out, "{value}").map_err(Error::from);
-        }
     }
 
-    // Fallback to print with escape.
+    // Fallback to print with escape, safe strings are printed as is.
     escape_formatter(
         out,
         state,
@@ -139,7 +167,7 @@ fn engine_formatter(
 ///
 /// # Returns
 ///
-/// A formatted string representing the rendered output of the provided JSON value.
+/// A safe string with the rendered output of the provided JSON value.
 ///
 /// # Notes
 ///
@@ -148,7 +176,7 @@ fn engine_formatter(
 ///
 /// The rendering process may involve recursive calls when dealing with nested JSON structures.
 ///
-fn render_value(state: &State, value: &minijinja::value::Value) -> String {
+fn render_value(state: &State, value: &minijinja::value::Value) -> minijinja::Value {
     fn _render_value_recursive(state: &State, value: &serde_json::Value) -> String {
         match value {
             serde_json::Value::Object(obj) => _render_object(state, obj),
@@ -174,7 +202,7 @@ fn render_value(state: &State, value: &minijinja::value::Value) -> String {
     }
 
     let data: serde_json::Value = serde_json::to_value(value).unwrap_or_default();
-    _render_value_recursive(state, &data)
+    minijinja::Value::from_safe_string(_render_value_recursive(state, &data))
 }
 
 /// Creates an HTML attribute Object as [`Attribute`].
@@ -202,13 +230,20 @@ pub fn create_attribute(
 /// Translate a string.
 ///
 /// Translation source is from payload key "@trans" which is a dict of
-/// original -> translated.
+/// original -> translated, or of catalogs by locale. The catalogs of the
+/// render locale and its fallback chain are used, see [`crate::locale`]. The
+/// message is formatted with the variables, see [`crate::message`] for the
+/// ICU plural, select and number arguments.
+///
+/// A term without translation is kept and reported in the json output as
+/// `missing_translations`.
 ///
 /// ```json
 /// {
 ///   "@trans": {
 ///     "Foo": "Baguette",
-///     "Hello @name!": "Bonjour @name!"
+///     "Hello @name!": "Bonjour @name!",
+///     "{count, plural, one {# item} other {# items}}": "{count, plural, one {# article} other {# articles}}"
 ///   }
 /// }
 /// ```
@@ -218,6 +253,8 @@ pub fn create_attribute(
 ///   -> Baguette
 /// {{ 'Hello @name!'|t({'@name': 'Bob'}) }}
 ///   -> Bonjour Bob!
+/// {{ '{count, plural, one {# item} other {# items}}'|t({'count': 2}) }}
+///   -> 2 articles
 /// ```
 ///
 pub fn t(
@@ -225,42 +262,72 @@ pub fn t(
     value: String,
     variables: Option<minijinja::value::Value>,
 ) -> String {
-    let translation = state.lookup("_translation").unwrap_or_default();
+    let translation = state.lookup(KEY_TRANSLATION).unwrap_or_default();
 
-    if translation.is_undefined() {
-        return value;
-    }
-
-    if let Ok(existing_trans) = translation.get_item(&minijinja::value::Value::from(value.clone()))
-    {
-        if existing_trans.is_undefined() {
-            return value;
+    let message = match translation.get_item(&minijinja::value::Value::from(value.clone())) {
+        Ok(translated) if translated.kind() == ValueKind::String => translated.to_string(),
+        _ => {
+            add_missing_translation(&value, state.name());
+            value
         }
+    };
 
-        // Strange serde behaviour adding quote around strings...
-        let mut result: String = existing_trans
-            .as_str()
-            .unwrap()
-            .to_string()
-            .replace('"', "");
-        if let Some(v) = variables {
-            if v.kind() == ValueKind::Map {
-                if let Ok(iter) = v.try_iter() {
-                    for key in iter {
-                        if let Some(str_key) = key.as_str() {
-                            if let Ok(value) = v.get_item(&key) {
-                                result = result.replace(str_key, &value.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-            // @todo [devtools] if not map, ie Seq or string: |t([...]) |t(XXX), should give warning.
+    let locale = state.lookup(KEY_LOCALE).unwrap_or_default();
+    let locale = locale.as_str().unwrap_or_default();
+
+    // @todo [devtools] if not map, ie Seq or string: |t([...]) |t(XXX), should give warning.
+    match variables {
+        Some(variables) if variables.kind() == ValueKind::Map => {
+            message::format(&message, &variables, locale)
         }
-        return result;
+        _ => message::format(&message, &minijinja::value::Value::UNDEFINED, locale),
     }
+}
+
+/// A `|t` term without translation and the template using it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingTranslation {
+    pub term: String,
+    pub template: String,
+}
+
+fn add_missing_translation(term: &str, template: &str) {
+    MISSING_TRANSLATIONS.with(|missing| {
+        let mut missing = missing.borrow_mut();
+        if !missing
+            .iter()
+            .any(|item| item.term == term && item.template == template)
+        {
+            missing.push(MissingTranslation {
+                term: term.to_string(),
+                template: template.to_string(),
+            });
+        }
+    });
+}
+
+/// Reset the terms without translation, called on each render.
+pub(crate) fn reset_missing_translations() {
+    MISSING_TRANSLATIONS.with(|missing| missing.borrow_mut().clear());
+}
+
+/// Get the terms without translation of the render.
+pub(crate) fn take_missing_translations() -> Vec<MissingTranslation> {
+    MISSING_TRANSLATIONS.with(|missing| missing.take())
+}
 
-    value
+/// Extract the literal `'...'|t` terms of the embedded templates, sorted, to
+/// build a translation catalog.
+pub fn extract_translations() -> Vec<String> {
+    let env = init_jinja_environnement();
+    let mut terms: Vec<String> = TEMPLATES_NAMES
+        .iter()
+        .filter_map(|name| env.get_template(name).ok())
+        .flat_map(|template| message::extract(template.source()))
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
 }
 
 /// Split a string based on a separator and return an array strings.
@@ -566,6 +633,185 @@ pub fn remove_attribute(
     remove(v, None, Some(name.as_str().unwrap_or_default()))
 }
 
+/// Set a `style` declaration of an [`Attribute`], a Seq or renderable.
+///
+/// An existing property is replaced, declarations keep their order.
+///
+/// ```jinja
+/// {{ {'style': 'color: red; margin: 0'}|set_style('color', 'blue') }}
+///   -> style="color: blue; margin: 0;"
+/// {{ {'@element': 'p'}|set_style('--gap', '1rem') }}
+///   -> <p style="--gap: 1rem;"></p>
+/// ```
+///
+pub fn set_style(
+    v: minijinja::Value,
+    property: String,
+    value: minijinja::Value,
+) -> Result<minijinja::Value, Error> {
+    let value = value.to_string();
+    update_attributes(v, vec!["style".to_string()], |attributes| {
+        attributes.set_style(&property, &value)
+    })
+}
+
+/// Remove a `style` declaration of an [`Attribute`], a Seq or renderable.
+///
+/// ```jinja
+/// {{ {'style': 'color: red; margin: 0'}|remove_style('color') }}
+///   -> style="margin: 0;"
+/// ```
+///
+pub fn remove_style(v: minijinja::Value, property: String) -> Result<minijinja::Value, Error> {
+    update_attributes(v, vec!["style".to_string()], |attributes| {
+        attributes.remove_style(&property)
+    })
+}
+
+/// Reset the ids generated by `unique_id`, called on each render.
+pub(crate) fn reset_unique_ids() {
+    UNIQUE_IDS.with(|ids| ids.borrow_mut().clear());
+}
+
+/// Generate an id unique in the render, from a prefix cleaned with
+/// `clean_id`. The same payload always renders the same ids.
+///
+/// ```jinja
+/// <input id="{{ unique_id('Field name') }}">
+///   -> <input id="field-name-1">
+/// <input id="{{ 'Field name'|unique_id }}">
+///   -> <input id="field-name-2">
+/// <input id="{{ unique_id() }}">
+///   -> <input id="id-1">
+/// ```
+///
+pub fn unique_id(prefix: Option<String>) -> String {
+    let prefix = clean_id(minijinja::Value::from(prefix.unwrap_or_default()));
+    let prefix = match prefix.trim_matches('-') {
+        "" => "id".to_string(),
+        prefix => prefix.to_string(),
+    };
+    let count = UNIQUE_IDS.with(|ids| {
+        let mut ids = ids.borrow_mut();
+        let count = ids.entry(prefix.clone()).or_default();
+        *count += 1;
+        *count
+    });
+
+    format!("{prefix}-{count}")
+}
+
+/// Set ARIA attributes of an [`Attribute`], a Seq or renderable.
+///
+/// The `aria-` prefix is optional, booleans are rendered as `"true"` or
+/// `"false"` and a none value removes the attribute. A Map sets multiple
+/// attributes.
+///
+/// ```jinja
+/// {{ {'id': 'menu'}|aria('expanded', false) }}
+///   -> aria-expanded="false" id="menu"
+/// {{ {'@element': 'button'}|aria({'expanded': true, 'controls': 'menu'}) }}
+///   -> <button aria-controls="menu" aria-expanded="true"></button>
+/// {{ {'aria-hidden': 'true'}|aria('hidden', none) }}
+///   ->
+/// ```
+///
+pub fn aria(
+    v: minijinja::Value,
+    name: minijinja::Value,
+    value: Option<minijinja::Value>,
+) -> Result<minijinja::Value, Error> {
+    let mut values: Vec<(String, minijinja::Value)> = Vec::new();
+    if name.kind() == ValueKind::Map {
+        if let Ok(keys) = name.try_iter() {
+            for key in keys {
+                let value = name.get_item(&key).unwrap_or_default();
+                values.push((key.to_string(), value));
+            }
+        }
+    } else {
+        values.push((name.to_string(), value.unwrap_or_default()));
+    }
+
+    let names: Vec<String> = values.iter().map(|(name, _)| aria_name(name)).collect();
+    update_attributes(v, names, |attributes| {
+        for (name, value) in values {
+            attributes.set_aria(&name, value);
+        }
+    })
+}
+
+/// Add ids to the `aria-describedby` of an [`Attribute`], a Seq or renderable.
+///
+/// ```jinja
+/// {{ {'aria-describedby': 'hint'}|describedby(['error', 'hint']) }}
+///   -> aria-describedby="hint error"
+/// ```
+///
+pub fn describedby(v: minijinja::Value, ids: minijinja::Value) -> Result<minijinja::Value, Error> {
+    add_id_refs(v, "aria-describedby", ids)
+}
+
+/// Add ids to the `aria-labelledby` of an [`Attribute`], a Seq or renderable.
+///
+/// ```jinja
+/// {% set label_id = unique_id('label') %}
+/// {{ {'role': 'dialog'}|labelledby(label_id) }}
+///   -> aria-labelledby="label-1" role="dialog"
+/// ```
+///
+pub fn labelledby(v: minijinja::Value, ids: minijinja::Value) -> Result<minijinja::Value, Error> {
+    add_id_refs(v, "aria-labelledby", ids)
+}
+
+fn add_id_refs(
+    v: minijinja::Value,
+    name: &str,
+    ids: minijinja::Value,
+) -> Result<minijinja::Value, Error> {
+    if ids.is_undefined() || ids.is_none() {
+        return Ok(v);
+    }
+    let ids: Vec<String> = _iter_seq_to_vec(ids);
+    update_attributes(v, vec![name.to_string()], |attributes| {
+        attributes.add_id_refs(name, ids)
+    })
+}
+
+/// Update attributes of a [`minijinja::Value`] by name with an [`Attribute`]
+/// closure.
+fn update_attributes(
+    v: minijinja::Value,
+    names: Vec<String>,
+    update: impl FnOnce(&mut Attribute),
+) -> Result<minijinja::Value, Error> {
+    match v.kind() {
+        ValueKind::Map => (),
+        // If Seq, probably a nested element, only the first item is updated as
+        // with add_class.
+        ValueKind::Seq => {
+            if let Ok(item) = v.get_item_by_index(0) {
+                return update_attributes(item, names, update);
+            }
+            return Ok(v);
+        }
+        _ => return Ok(v),
+    }
+
+    match get_renderable_type_from(&v) {
+        RenderableType::Element | RenderableType::Component => {
+            Ok(update_attributes_in_json(&v, &names, update))
+        }
+        RenderableType::Template => Ok(v),
+        // Everything else is treated as an Attribute.
+        _ => {
+            let mut attributes: Attribute = v.into();
+            update(&mut attributes);
+            Ok(attributes.into())
+        }
+    }
+}
+
 /// Removes a class or attribute from a [`minijinja::Value`].
 ///
 /// # Arguments
@@ -664,8 +910,26 @@ pub fn has_class(
     Ok(minijinja::value::Value::from(false))
 }
 
+/// Seed the generator of `random()` for the render.
+pub(crate) fn seed_random(seed: u64) {
+    #[cfg(feature = "random")]
+    RANDOM.with(|random| *random.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+    #[cfg(not(feature = "random"))]
+    let _ = seed;
+}
+
+/// Drop the generator of the render, called at the end of each render.
+pub(crate) fn reset_random() {
+    #[cfg(feature = "random")]
+    RANDOM.with(|random| *random.borrow_mut() = None);
+}
+
 /// Generate a random string from a number of type i32.
 ///
+/// Numbers are taken from the generator seeded for the render, the same
+/// payload and seed render the same numbers. Outside of a render, the thread
+/// generator is used.
+///
 /// ```jinja
 /// {{ random() }}
 ///   -> 2005036924
@@ -677,7 +941,10 @@ pub fn generate_random_string() -> Result<minijinja::value::Value, Error> {
 
     // With rand and getrandom.
     #[cfg(feature = "random")]
-    let rand: i32 = thread_rng().gen::<i32>();
+    let rand: i32 = RANDOM.with(|random| match random.borrow_mut().as_mut() {
+        Some(rng) => rng.gen::<i32>(),
+        None => thread_rng().gen::<i32>(),
+    });
     #[cfg(feature = "random")]
     Ok(minijinja::value::Value::from(rand))
 }
@@ -0,0 +1,61 @@
+//! Errors returned by the render.
+
+use crate::limits::LimitError;
+
+use std::fmt;
+
+/// Error of a render call.
+#[derive(Debug)]
+pub enum Error {
+    /// The payload is not a valid JSON.
+    Payload(serde_json::Error),
+    /// The output format is not a built-in or registered format.
+    UnknownOutput(String),
+    /// A custom formatter is registered with a built-in format name.
+    ReservedOutput(String),
+    /// A limit of the render option `limits` is exceeded.
+    Limit(LimitError),
+    /// A template of the payload or the Design System failed to render.
+    Template(minijinja::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Payload(error) => write!(f, "Invalid payload: {}", error),
+            Error::UnknownOutput(name) => write!(f, "Unknown output: {}", name),
+            Error::ReservedOutput(name) => write!(f, "Reserved output: {}", name),
+            Error::Limit(error) => write!(f, "Render limit exceeded: {}", error),
+            Error::Template(error) => write!(f, "Template error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Payload(error) => Some(error),
+            Error::UnknownOutput(_) | Error::ReservedOutput(_) => None,
+            Error::Limit(error) => Some(error),
+            Error::Template(error) => Some(error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Payload(error)
+    }
+}
+
+impl From<LimitError> for Error {
+    fn from(error: LimitError) -> Self {
+        Error::Limit(error)
+    }
+}
+
+impl From<minijinja::Error> for Error {
+    fn from(error: minijinja::Error) -> Self {
+        Error::Template(error)
+    }
+}
@@ -0,0 +1,313 @@
+//! Control the whitespace of the rendered HTML.
+//!
+//! Used by the render options `minify` and `pretty` for the HTML outputs and
+//! the HTML parts of the `json` output. Tags are kept as rendered, only the
+//! whitespace between them is changed. Content of `<pre>`, `<textarea>`,
+//! `<script>` and `<style>` is never altered.
+
+use crate::renderable::VOID_ELEMENTS;
+
+/// Elements with content kept as is.
+const PRESERVED_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Elements rendered in the text flow, whitespace next to them is kept.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "button", "cite", "code", "data", "del", "dfn", "em", "i",
+    "img", "input", "ins", "kbd", "label", "mark", "q", "s", "samp", "select", "small", "span",
+    "strong", "sub", "sup", "textarea", "time", "u", "var",
+];
+
+/// Indentation of a level for the pretty print.
+const INDENT: &str = "  ";
+
+/// A piece of the HTML, tags are kept as rendered.
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// Start tag with its lowercase name.
+    Open(&'a str, String),
+    Close(&'a str),
+    /// Void or self-closing tag, comment or doctype.
+    Single(&'a str),
+    Text(&'a str),
+    /// Content of a preserved element.
+    Raw(&'a str),
+}
+
+/// Remove the whitespace used for formatting.
+///
+/// Whitespace including a line break next to a block tag is removed, any
+/// other whitespace is collapsed to a single space.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::format::minify;
+///
+/// let html = "\n<p>\n  Hello   <b>world</b>\n</p>\n<pre> a\n b </pre>";
+///
+/// assert_eq!(minify(html), "<p>Hello <b>world</b></p><pre> a\n b </pre>");
+/// assert_eq!(minify("<p>Hello\n<b>world</b></p>"), "<p>Hello <b>world</b></p>");
+/// ```
+pub fn minify(html: &str) -> String {
+    let tokens = tokenize(html);
+    let mut output = String::with_capacity(html.len());
+    for (index, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open(tag, _) | Token::Close(tag) | Token::Single(tag) => output.push_str(tag),
+            Token::Raw(raw) => output.push_str(raw),
+            Token::Text(text) => {
+                let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+                let is_inline = (is_inline(previous), is_inline(tokens.get(index + 1)));
+                output.push_str(&collapse(text, is_inline));
+            }
+        }
+    }
+
+    output
+}
+
+/// Indent the HTML with a tag or a text per line.
+///
+/// Elements with only a text are kept on a single line.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::format::pretty;
+///
+/// let html = "<div><p>\nHello</p><br><span></span></div>";
+///
+/// assert_eq!(pretty(html), "<div>\n  <p>Hello</p>\n  <br>\n  <span></span>\n</div>");
+/// ```
+pub fn pretty(html: &str) -> String {
+    let tokens: Vec<Token> = tokenize(html)
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Text(text) if text.trim().is_empty()))
+        .collect();
+
+    let mut output = String::with_capacity(html.len());
+    let mut depth: usize = 0;
+    let mut index = 0;
+    while index < tokens.len() {
+        let line = match &tokens[index] {
+            Token::Open(tag, name) => {
+                // Keep an element with a single text or raw content inline.
+                let (content, next) = match tokens.get(index + 1) {
+                    Some(Token::Text(text)) => {
+                        (collapse(text, (false, false)).trim().to_string(), index + 2)
+                    }
+                    Some(Token::Raw(raw)) => (raw.to_string(), index + 2),
+                    _ => (String::new(), index + 1),
+                };
+                match tokens.get(next) {
+                    Some(Token::Close(close)) if is_closing(close, name) => {
+                        index = next;
+                        format!("{tag}{content}{close}")
+                    }
+                    _ => {
+                        push_indented(&mut output, depth, tag);
+                        depth += 1;
+                        index += 1;
+                        continue;
+                    }
+                }
+            }
+            Token::Close(tag) => {
+                depth = depth.saturating_sub(1);
+                tag.to_string()
+            }
+            Token::Single(tag) => tag.to_string(),
+            Token::Text(text) => collapse(text, (false, false)).trim().to_string(),
+            Token::Raw(raw) => raw.to_string(),
+        };
+        push_indented(&mut output, depth, &line);
+        index += 1;
+    }
+
+    output
+}
+
+/// Add a part on a new line, without empty lines.
+pub(crate) fn push_line(output: &mut String, line: &str) {
+    if line.is_empty() {
+        return;
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output.push_str(line);
+}
+
+fn push_indented(output: &mut String, depth: usize, line: &str) {
+    push_line(output, &format!("{}{}", INDENT.repeat(depth), line));
+}
+
+/// Check a closing tag match the element name.
+fn is_closing(tag: &str, name: &str) -> bool {
+    tag[2..]
+        .trim_end_matches('>')
+        .trim()
+        .eq_ignore_ascii_case(name)
+}
+
+/// Check a token is an inline tag or a text, `None` is the document edge.
+fn is_inline(token: Option<&Token>) -> bool {
+    let tag = match token {
+        Some(Token::Open(_, name)) => return INLINE_ELEMENTS.contains(&name.as_str()),
+        Some(Token::Close(tag)) => &tag[2..],
+        Some(Token::Single(tag)) if !tag.starts_with("<!") && !tag.starts_with("<?") => &tag[1..],
+        Some(Token::Text(_)) => return true,
+        _ => return false,
+    };
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    INLINE_ELEMENTS.contains(&name.as_str())
+}
+
+/// Collapse whitespace of a text, removed at the edges with a line break
+/// unless the tag on that edge is inline.
+fn collapse(text: &str, (is_inline_start, is_inline_end): (bool, bool)) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, char)) = chars.next() {
+        if !char.is_whitespace() {
+            output.push(char);
+            continue;
+        }
+        let mut end = start + char.len_utf8();
+        while let Some((position, char)) = chars.next_if(|(_, char)| char.is_whitespace()) {
+            end = position + char.len_utf8();
+        }
+        let is_edge = (start == 0 && !is_inline_start) || (end == text.len() && !is_inline_end);
+        if !(is_edge && text[start..end].contains('\n')) {
+            output.push(' ');
+        }
+    }
+
+    output
+}
+
+/// Split the HTML in tags, texts and preserved content.
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let start = rest.find('<').unwrap_or(rest.len());
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+            rest = &rest[start..];
+            continue;
+        }
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |end| end + 3);
+            tokens.push(Token::Single(&rest[..end]));
+            rest = &rest[end..];
+        } else if rest.starts_with("</") {
+            let end = tag_end(rest);
+            tokens.push(Token::Close(&rest[..end]));
+            rest = &rest[end..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = tag_end(rest);
+            tokens.push(Token::Single(&rest[..end]));
+            rest = &rest[end..];
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let end = tag_end(rest);
+            let tag = &rest[..end];
+            rest = &rest[end..];
+            let name = tag[1..]
+                .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+
+            if tag.ends_with("/>") || VOID_ELEMENTS.contains(&name.as_str()) {
+                tokens.push(Token::Single(tag));
+            } else if PRESERVED_ELEMENTS.contains(&name.as_str()) {
+                let closing = format!("</{name}");
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&closing)
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Open(tag, name));
+                if end > 0 {
+                    tokens.push(Token::Raw(&rest[..end]));
+                }
+                rest = &rest[end..];
+            } else {
+                tokens.push(Token::Open(tag, name));
+            }
+        } else {
+            tokens.push(Token::Text(&rest[..1]));
+            rest = &rest[1..];
+        }
+    }
+
+    tokens
+}
+
+/// Position after the end of a tag, ignoring `>` in quoted values.
+fn tag_end(html: &str) -> usize {
+    let mut quote: Option<char> = None;
+    for (position, char) in html.char_indices() {
+        match (quote, char) {
+            (Some(open), _) if char == open => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => quote = Some(char),
+            (None, '>') => return position + 1,
+            _ => (),
+        }
+    }
+
+    html.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_preserved() {
+        let html = "<textarea>\n  a\n</textarea>\n<script>\nlet a = '<b>';\n</script>\n<STYLE> p { } </STYLE>";
+
+        assert_eq!(
+            minify(html),
+            "<textarea>\n  a\n</textarea><script>\nlet a = '<b>';\n</script><STYLE> p { } </STYLE>"
+        );
+    }
+
+    #[test]
+    fn test_minify_inline_whitespace() {
+        let html = "<span>a</span> <span>b</span>\n<a title=\"x > y\">c</a>  d";
+
+        assert_eq!(
+            minify(html),
+            "<span>a</span> <span>b</span> <a title=\"x > y\">c</a> d"
+        );
+    }
+
+    #[test]
+    fn test_minify_inline_line_break() {
+        let html = "<p>Hello\n<b>world</b>\n!</p>\n<div>\n<img src=\"a.png\">\ntext\n</div>";
+
+        assert_eq!(
+            minify(html),
+            "<p>Hello <b>world</b> !</p><div><img src=\"a.png\"> text</div>"
+        );
+    }
+
+    #[test]
+    fn test_pretty_document() {
+        let html = "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><!-- a --></head><body>\n<div>\n<p>Hello\n<b>world</b>\n</p>\n</div><script>\nlet a;\n</script></body></html>";
+
+        assert_eq!(
+            pretty(html),
+            "<!DOCTYPE html>\n<html>\n  <head>\n    <meta charset=\"utf-8\">\n    <!-- a -->\n  </head>\n  <body>\n    <div>\n      <p>\n        Hello\n        <b>world</b>\n      </p>\n    </div>\n    <script>\nlet a;\n</script>\n  </body>\n</html>"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Generate *resource hints* for the libraries needed by the render.
+//!
+//! Once every library is collected, hints are built as `preconnect` for
+//! distinct external origins, `preload` for stylesheets and critical scripts
+//! and `modulepreload` for `type="module"` scripts.
+
+use crate::{attribute::Attribute, renderable::Html, LIBRARIES_INTEGRITY};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A single resource hint rendered as a `<link>` tag.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ResourceHint {
+    pub rel: String,
+    pub href: String,
+    /// Destination of a `preload`, as `style` or `script`.
+    #[serde(rename = "as", skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crossorigin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+}
+
+impl ResourceHint {
+    fn new(rel: &str, href: &str) -> Self {
+        Self {
+            rel: rel.to_string(),
+            href: href.to_string(),
+            destination: None,
+            crossorigin: None,
+            integrity: None,
+        }
+    }
+}
+
+impl Html for ResourceHint {
+    fn to_html_string(&self) -> String {
+        let mut attributes = Attribute::new();
+        attributes.add_attr("rel", vec![self.rel.as_str()]);
+        attributes.add_attr("href", vec![self.href.as_str()]);
+        if let Some(destination) = &self.destination {
+            attributes.add_attr("as", vec![destination.as_str()]);
+        }
+        if let Some(crossorigin) = &self.crossorigin {
+            attributes.add_attr("crossorigin", vec![crossorigin.as_str()]);
+        }
+        if let Some(integrity) = &self.integrity {
+            attributes.add_attr("integrity", vec![integrity.as_str()]);
+        }
+
+        format!("<link{} />", attributes)
+    }
+}
+
+/// List of resource hints, `preconnect` first, without duplicates.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(transparent)]
+pub struct ResourceHints {
+    hints: Vec<ResourceHint>,
+}
+
+impl ResourceHints {
+    pub fn new() -> Self {
+        ResourceHints::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    pub fn hints(&self) -> &[ResourceHint] {
+        &self.hints
+    }
+
+    /// Add `preload` hints from rendered stylesheets `<link>` tags.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::hints::ResourceHints;
+    ///
+    /// let mut hints = ResourceHints::new();
+    /// hints.add_stylesheets(
+    ///     r#"<link type="text/css" rel="stylesheet" href="https://cdn.dilla.io/a.css">"#,
+    /// );
+    ///
+    /// assert_eq!(hints.hints()[0].rel, "preconnect");
+    /// assert_eq!(hints.hints()[0].href, "https://cdn.dilla.io");
+    /// assert_eq!(hints.hints()[1].rel, "preload");
+    /// assert_eq!(hints.hints()[1].destination, Some("style".to_string()));
+    /// ```
+    pub fn add_stylesheets(&mut self, html: &str) {
+        for tag in html.split("<link").skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default();
+            let is_stylesheet = tag_attribute(tag, "rel")
+                .map(|rel| rel.split_whitespace().any(|rel| rel == "stylesheet"))
+                .unwrap_or(false);
+            if !is_stylesheet {
+                continue;
+            }
+            if let Some(href) = tag_attribute(tag, "href") {
+                let mut hint = ResourceHint::new("preload", &href);
+                hint.destination = Some("style".to_string());
+                hint.crossorigin = tag_attribute(tag, "crossorigin");
+                hint.integrity = tag_attribute(tag, "integrity");
+                self.add(hint);
+            }
+        }
+    }
+
+    /// Add `modulepreload` hints for module scripts and `preload` hints for
+    /// critical scripts, ie. without `async` or `defer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::hints::ResourceHints;
+    /// use indexmap::IndexMap;
+    /// use serde_json::json;
+    ///
+    /// let mut scripts = IndexMap::new();
+    /// scripts.insert("module.js".to_string(), json!({"type": "module"}));
+    /// scripts.insert("async.js".to_string(), json!({"async": "true"}));
+    /// scripts.insert("critical.js".to_string(), json!({}));
+    ///
+    /// let mut hints = ResourceHints::new();
+    /// hints.add_scripts(&scripts);
+    ///
+    /// assert_eq!(hints.hints().len(), 2);
+    /// assert_eq!(hints.hints()[0].rel, "modulepreload");
+    /// assert_eq!(hints.hints()[1].href, "critical.js");
+    /// ```
+    pub fn add_scripts(&mut self, scripts: &IndexMap<String, Value>) {
+        for (url, attributes) in scripts {
+            let is_module = attributes.get("type").and_then(Value::as_str) == Some("module");
+            let hint = if is_module {
+                ResourceHint::new("modulepreload", url)
+            } else if is_set(attributes, "async") || is_set(attributes, "defer") {
+                continue;
+            } else {
+                let mut hint = ResourceHint::new("preload", url);
+                hint.destination = Some("script".to_string());
+                hint
+            };
+
+            self.add(script_integrity(hint, attributes));
+        }
+    }
+
+    /// Add a hint and the `preconnect` for its origin if external.
+    fn add(&mut self, hint: ResourceHint) {
+        if let Some(origin) = origin(&hint.href) {
+            let mut preconnect = ResourceHint::new("preconnect", &origin);
+            preconnect.crossorigin.clone_from(&hint.crossorigin);
+            if !self.hints.contains(&preconnect) {
+                let position = self
+                    .hints
+                    .iter()
+                    .take_while(|hint| hint.rel == "preconnect")
+                    .count();
+                self.hints.insert(position, preconnect);
+            }
+        }
+
+        if !self.hints.iter().any(|existing| existing.href == hint.href) {
+            self.hints.push(hint);
+        }
+    }
+}
+
+impl Html for ResourceHints {
+    fn to_html_string(&self) -> String {
+        self.hints
+            .iter()
+            .map(|hint| hint.to_html_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Set integrity and crossorigin as the script tag will have it.
+fn script_integrity(mut hint: ResourceHint, attributes: &Value) -> ResourceHint {
+    hint.integrity = attributes
+        .get("integrity")
+        .and_then(Value::as_str)
+        .or_else(|| LIBRARIES_INTEGRITY.get(hint.href.as_str()).copied())
+        .map(str::to_string);
+    hint.crossorigin = attributes
+        .get("crossorigin")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| hint.integrity.as_ref().map(|_| "anonymous".to_string()));
+
+    hint
+}
+
+/// Check a script attribute is set and not false.
+fn is_set(attributes: &Value, name: &str) -> bool {
+    match attributes.get(name) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => value != "false",
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    }
+}
+
+/// Get the origin of an external url, `None` for local url.
+pub(crate) fn origin(url: &str) -> Option<String> {
+    let (scheme, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        ("https://", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        ("http://", rest)
+    } else if let Some(rest) = url.strip_prefix("//") {
+        ("//", rest)
+    } else {
+        return None;
+    };
+
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    match host.is_empty() {
+        true => None,
+        false => Some(format!("{scheme}{host}")),
+    }
+}
+
+/// Get an attribute value from a rendered tag.
+pub(crate) fn tag_attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {name}=\"");
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')? + start;
+
+    Some(tag[start..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(
+            origin("https://cdn.dilla.io/lib/a.js?v=1"),
+            Some("https://cdn.dilla.io".to_string())
+        );
+        assert_eq!(
+            origin("//cdn.dilla.io/a.js"),
+            Some("//cdn.dilla.io".to_string())
+        );
+        assert_eq!(origin("/lib/a.js"), None);
+        assert_eq!(origin("a.js"), None);
+    }
+
+    #[test]
+    fn test_preconnect_distinct_origins() {
+        let mut scripts = IndexMap::new();
+        scripts.insert("https://a.dilla.io/1.js".to_string(), json!({}));
+        scripts.insert("https://a.dilla.io/2.js".to_string(), json!({}));
+        scripts.insert("https://b.dilla.io/3.js".to_string(), json!({}));
+
+        let mut hints = ResourceHints::new();
+        hints.add_stylesheets(
+            r#"<link rel="stylesheet" href="https://a.dilla.io/1.css"><link rel="icon" href="https://c.dilla.io/i.png">"#,
+        );
+        hints.add_scripts(&scripts);
+
+        let rels: Vec<(&str, &str)> = hints
+            .hints()
+            .iter()
+            .map(|hint| (hint.rel.as_str(), hint.href.as_str()))
+            .collect();
+        assert_eq!(
+            rels,
+            vec![
+                ("preconnect", "https://a.dilla.io"),
+                ("preconnect", "https://b.dilla.io"),
+                ("preload", "https://a.dilla.io/1.css"),
+                ("preload", "https://a.dilla.io/1.js"),
+                ("preload", "https://a.dilla.io/2.js"),
+                ("preload", "https://b.dilla.io/3.js"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_hint_integrity() {
+        let mut scripts = IndexMap::new();
+        scripts.insert(
+            "https://cdn.dilla.io/test.remote.js".to_string(),
+            json!({"defer": false}),
+        );
+
+        let mut hints = ResourceHints::new();
+        hints.add_scripts(&scripts);
+
+        let expected = "<link rel=\"preconnect\" href=\"https://cdn.dilla.io\" crossorigin=\"anonymous\" />\n<link rel=\"preload\" href=\"https://cdn.dilla.io/test.remote.js\" as=\"script\" crossorigin=\"anonymous\" integrity=\"sha384-oqVuAfXRKap7fdgcCY5uykM6+R9GqQ8K/uxy9rx7HNQlGYl1kPzQho1wx4JwY8wC\" />";
+        assert_eq!(hints.to_html_string(), expected);
+    }
+}
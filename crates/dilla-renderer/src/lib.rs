@@ -18,13 +18,31 @@
 
 pub mod attribute;
 pub mod bubbable;
+pub mod bundle;
+pub mod dom;
+pub mod email;
 pub mod engine;
+pub mod error;
+pub mod format;
+pub mod hints;
+pub mod limits;
+pub mod locale;
+pub mod message;
+pub mod options;
+pub mod output;
+pub mod policy;
 pub mod renderable;
 pub mod renderer;
 pub mod scoped;
+pub mod source_map;
+pub mod timing;
 
+pub use error::Error;
+use options::RenderOptions;
+use output::OutputFormat;
 use renderer::{Output, Renderer};
-use serde_json::Error;
+#[cfg(feature = "profiling")]
+use timing::TimingNode;
 
 /// Keywords prefix to identify properties in the payload.
 pub(crate) const KEY_PREFIX: &str = "@";
@@ -45,21 +63,95 @@ include!(concat!(env!("OUT_DIR"), "/codegen_config.rs"));
 ///     * `stylesheet`: The HTML list of `<link>` tags
 ///     * `javascript`: A list of javascript files from libraries as `name => {options}`.
 ///     * `variables`: The HTML `@variables` wrapped in a `<style>` tag
+///     * `javascript_inline`: Inline scripts from the payload as `name => {script, options}`, only if any.
+///     * `missing_translations`: A list of the `|t` terms without translation as `{term, template}`, only if any.
+///     * `hints`: A list of resource hints, only with option `resource_hints`
+///     * `timing`: A tree of render times as `{name, count, total, self, children}` by component, only with option `profiling`
+///     * `source_map`: A list of renderables as `{pointer, type, name, start, end}` with the byte range in `body`, only with option `source_map`
+///   * `fragments`: As `json` with `fragments` instead of `body`, a list of each top-level item as `{id, html}`, `id` from optional `@id`
 ///   * `full`: Whole HTML page wrapped in `<html>` tag
+///   * `dom`: A json DOM tree as `attached` and `body` list of nodes with `type`, `tag`, `attributes`, `children` and `text`
+///   * `email`: Whole HTML page for email clients with styles inlined, custom properties resolved and no scripts
+///   * `full_inline`: Whole HTML page with local CSS and JS libraries inlined from option `asset_root`
 ///   * `_test`: Test is only HTML body without head, styles and libraries, for test purpose
 ///   * `_test_full`: All HTML parts not wrapped in `<html>` tag, for test purpose
 ///   * `_logs`: Display on logs, mostly debug and internal test purpose
+///   * Any name of a formatter registered with [`output::register_formatter`]
 ///
 /// # Returns
 ///
-/// * `Result<String, Error>` - A Result containing the rendered output as a String if successful, or an Error if any error occurs.
+/// * `Result<String, Error>` - A Result containing the rendered output as a String if successful, or an Error if the payload or the output is not valid.
 ///
 pub fn render(payload: &str, output: &str) -> Result<String, Error> {
+    let format: OutputFormat = output.parse()?;
+    render_with_options(payload, format, RenderOptions::default())
+}
+
+/// Render a Json String value like [`render`] with specific render options.
+///
+/// # Arguments
+///
+/// * `payload`: The JSON payload string to be rendered, following Dilla format API
+/// * `output`: The [`OutputFormat`] desired, see [`render`]
+/// * `options`: The [`RenderOptions`] as CSP nonce, scoped local variables or minify
+///
+/// # Returns
+///
+/// * `Result<String, Error>` - A Result containing the rendered output as a String if successful, or an Error if any error occurs, including [`Error::Limit`] when a limit of the option `limits` is exceeded.
+///
+pub fn render_with_options(
+    payload: &str,
+    output: OutputFormat,
+    options: RenderOptions,
+) -> Result<String, Error> {
+    let renderer = build_renderer(payload, &output, options)?;
+
+    Ok(renderer.to_output_string(&output))
+}
+
+/// Render a Json String value like [`render_with_options`] and get the tree of
+/// render times by component, needs the `profiling` feature.
+///
+/// # Returns
+///
+/// * `Result<(String, TimingNode), Error>` - A Result containing the rendered output and the timing tree if successful, or an Error if any error occurs.
+///
+#[cfg(feature = "profiling")]
+pub fn render_profiled(
+    payload: &str,
+    output: OutputFormat,
+    options: RenderOptions,
+) -> Result<(String, TimingNode), Error> {
+    let renderer = build_renderer(payload, &output, options.with_profiling(true))?;
+
+    Ok((
+        renderer.to_output_string(&output),
+        renderer.output.timing.clone(),
+    ))
+}
+
+fn build_renderer(
+    payload: &str,
+    output: &OutputFormat,
+    mut options: RenderOptions,
+) -> Result<Renderer, Error> {
+    // A custom format can be built without being registered.
+    if let OutputFormat::Custom(name) = output {
+        if output::formatter(name).is_none() {
+            return Err(Error::UnknownOutput(name.clone()));
+        }
+    }
     let json: serde_json::Value = serde_json::from_str::<serde_json::Value>(payload)?;
-    let mut renderer: Renderer = Renderer::new();
+    if *output == OutputFormat::Dom {
+        options.dom = true;
+    }
+    let mut renderer: Renderer = Renderer::with_options(options);
     renderer.render(&json);
 
-    Ok(renderer.to_output_string(output))
+    match renderer.error.take() {
+        Some(error) => Err(error),
+        None => Ok(renderer),
+    }
 }
 
 /// Render a Json String into Json String output. Shortcut for render(payload, "json").
@@ -0,0 +1,217 @@
+//! Limit the resources of a render for untrusted payloads.
+//!
+//! Limits are set with the render option `limits` and checked on the current
+//! thread for the whole render, including nested slots rendered from the
+//! templates. The first limit exceeded or template error stops the render,
+//! the error is returned instead of the output.
+
+use crate::Error;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+
+/// Limits of a render, no limit by default.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::{limits::*, options::RenderOptions, render_with_options, Error};
+///
+/// let payload = r#"{"@element": "div", "@content": {"@element": "p"}}"#;
+/// let options = RenderOptions::new().with_limits(RenderLimits::new().with_max_depth(1));
+/// let result = render_with_options(payload, "_test".parse().unwrap(), options);
+///
+/// assert!(matches!(result, Err(Error::Limit(LimitError::Depth(1)))));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderLimits {
+    /// Maximum nesting depth of renderables.
+    pub max_depth: Option<usize>,
+    /// Maximum number of renderables.
+    pub max_renderables: Option<usize>,
+    /// Maximum size of the body in bytes.
+    pub max_output_size: Option<usize>,
+    /// MiniJinja fuel of each template render, stop loops in `@template`.
+    pub template_fuel: Option<u64>,
+}
+
+impl RenderLimits {
+    pub fn new() -> Self {
+        RenderLimits::default()
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn with_max_renderables(mut self, count: usize) -> Self {
+        self.max_renderables = Some(count);
+        self
+    }
+
+    pub fn with_max_output_size(mut self, size: usize) -> Self {
+        self.max_output_size = Some(size);
+        self
+    }
+
+    pub fn with_template_fuel(mut self, fuel: u64) -> Self {
+        self.template_fuel = Some(fuel);
+        self
+    }
+}
+
+/// A render limit exceeded, with the value of the limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    Depth(usize),
+    Renderables(usize),
+    OutputSize(usize),
+    TemplateFuel(u64),
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::Depth(max) => write!(f, "max depth of {} renderables", max),
+            LimitError::Renderables(max) => write!(f, "max of {} renderables", max),
+            LimitError::OutputSize(max) => write!(f, "max output size of {} bytes", max),
+            LimitError::TemplateFuel(fuel) => write!(f, "template fuel of {}", fuel),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+#[derive(Default)]
+struct Usage {
+    limits: RenderLimits,
+    depth: usize,
+    renderables: usize,
+    error: Option<Error>,
+}
+
+thread_local! {
+    static USAGE: RefCell<Usage> = RefCell::new(Usage::default());
+}
+
+/// Start to check the limits of a render on the current thread.
+pub(crate) fn start(limits: &RenderLimits) {
+    USAGE.with(|usage| {
+        *usage.borrow_mut() = Usage {
+            limits: limits.clone(),
+            ..Usage::default()
+        };
+    });
+}
+
+/// Stop to check the limits and get the error of the render, if any.
+pub(crate) fn finish() -> Option<Error> {
+    USAGE.with(|usage| std::mem::take(&mut *usage.borrow_mut()).error)
+}
+
+/// Check a limit is exceeded and the render must stop.
+pub(crate) fn is_exceeded() -> bool {
+    USAGE.with(|usage| usage.borrow().error.is_some())
+}
+
+/// Stop the render with an error, the first one is kept.
+pub(crate) fn exceed(error: impl Into<Error>) {
+    USAGE.with(|usage| {
+        usage.borrow_mut().error.get_or_insert(error.into());
+    });
+}
+
+/// Guard of a renderable nesting level, left when dropped.
+pub(crate) struct Depth;
+
+impl Drop for Depth {
+    fn drop(&mut self) {
+        USAGE.with(|usage| {
+            let mut usage = usage.borrow_mut();
+            usage.depth = usage.depth.saturating_sub(1);
+        });
+    }
+}
+
+/// Enter a renderable, `None` when a limit is exceeded.
+pub(crate) fn enter() -> Option<Depth> {
+    USAGE.with(|usage| {
+        let mut usage = usage.borrow_mut();
+        if usage.error.is_some() {
+            return None;
+        }
+        usage.depth += 1;
+        usage.renderables += 1;
+
+        let error = match (usage.limits.max_depth, usage.limits.max_renderables) {
+            (Some(max), _) if usage.depth > max => Some(LimitError::Depth(max)),
+            (_, Some(max)) if usage.renderables > max => Some(LimitError::Renderables(max)),
+            _ => None,
+        };
+        match error {
+            Some(error) => {
+                usage.depth -= 1;
+                usage.error = Some(error.into());
+                None
+            }
+            None => Some(Depth),
+        }
+    })
+}
+
+/// Check the size of an output, `false` when the limit is exceeded.
+pub(crate) fn check_output_size(size: usize) -> bool {
+    let max = USAGE.with(|usage| usage.borrow().limits.max_output_size);
+    match max {
+        Some(max) if size > max => {
+            exceed(LimitError::OutputSize(max));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Stop the render with a template error, out of fuel is a limit exceeded.
+pub(crate) fn template_error(error: minijinja::Error) {
+    if error.kind() != minijinja::ErrorKind::OutOfFuel {
+        return exceed(error);
+    }
+    let fuel = USAGE.with(|usage| usage.borrow().limits.template_fuel);
+    exceed(LimitError::TemplateFuel(fuel.unwrap_or_default()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_and_renderables() {
+        start(
+            &RenderLimits::new()
+                .with_max_depth(2)
+                .with_max_renderables(3),
+        );
+        {
+            let _first = enter().unwrap();
+            let _second = enter().unwrap();
+            assert!(enter().is_none());
+        }
+        assert!(matches!(finish(), Some(Error::Limit(LimitError::Depth(2)))));
+
+        start(&RenderLimits::new().with_max_renderables(1));
+        drop(enter());
+        assert!(enter().is_none());
+        assert!(is_exceeded());
+        assert!(matches!(
+            finish(),
+            Some(Error::Limit(LimitError::Renderables(1)))
+        ));
+
+        // No limit once finished.
+        assert!(enter().is_some());
+        assert!(check_output_size(usize::MAX));
+    }
+}
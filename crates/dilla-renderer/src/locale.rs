@@ -0,0 +1,203 @@
+//! Handle the locale of a render.
+//!
+//! The locale is set with the render option `locale` or the payload key
+//! **@lang**, the option wins. It selects the **@trans** catalogs with a
+//! fallback chain, as `fr-CA`, `fr` then the option `fallback_locale`, the
+//! plural rules of the `|t` filter and the `lang` and `dir` of the full
+//! document. Templates get it as the `locale` variable.
+
+use minijinja::HtmlEscape;
+
+/// Name of the environment global with the locale of the render.
+pub(crate) const KEY_LOCALE: &str = "locale";
+
+/// Name of the environment global with the translations of the render, shared
+/// with the nested renderers.
+pub(crate) const KEY_TRANSLATION: &str = "_translation";
+
+/// Languages written right to left.
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "ks", "ku", "ps", "sd", "ug", "ur", "yi",
+];
+
+/// Normalize a locale as a BCP 47 tag: `fr_ca` to `fr-CA`.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::locale::normalize;
+///
+/// assert_eq!(normalize(" fr_ca "), "fr-CA");
+/// assert_eq!(normalize("zh-hant-TW"), "zh-Hant-TW");
+/// ```
+pub fn normalize(locale: &str) -> String {
+    locale
+        .trim()
+        .split(['-', '_'])
+        .filter(|subtag| !subtag.is_empty())
+        .enumerate()
+        .map(|(index, subtag)| match (index, subtag.len()) {
+            (0, _) => subtag.to_ascii_lowercase(),
+            (_, 2) => subtag.to_ascii_uppercase(),
+            (_, 4) => {
+                let (first, rest) = subtag.split_at(1);
+                first.to_ascii_uppercase() + &rest.to_ascii_lowercase()
+            }
+            _ => subtag.to_ascii_lowercase(),
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Get the locales to look for a translation, most specific first.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::locale::fallback_chain;
+///
+/// assert_eq!(fallback_chain("fr-CA", Some("en")), vec!["fr-CA", "fr", "en"]);
+/// assert_eq!(fallback_chain("en-US", Some("en")), vec!["en-US", "en"]);
+/// ```
+pub fn fallback_chain(locale: &str, fallback: Option<&str>) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut subtags: Vec<&str> = Vec::new();
+    let locale = normalize(locale);
+    for subtag in locale.split('-').filter(|subtag| !subtag.is_empty()) {
+        subtags.push(subtag);
+        chain.insert(0, subtags.join("-"));
+    }
+    for fallback in fallback
+        .into_iter()
+        .flat_map(|fallback| fallback_chain(fallback, None))
+    {
+        if !chain.contains(&fallback) {
+            chain.push(fallback);
+        }
+    }
+    chain
+}
+
+/// Get the language of a locale, `fr` for `fr-CA`.
+pub fn language(locale: &str) -> String {
+    normalize(locale)
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Check a locale is written right to left.
+pub fn is_rtl(locale: &str) -> bool {
+    RTL_LANGUAGES.contains(&language(locale).as_str())
+}
+
+/// Get the `lang` and `dir` attributes of the HTML document.
+pub fn html_attributes(locale: Option<&str>) -> String {
+    match locale {
+        Some(locale) if is_rtl(locale) => {
+            format!(" lang=\"{}\" dir=\"rtl\"", HtmlEscape(&normalize(locale)))
+        }
+        Some(locale) => format!(" lang=\"{}\"", HtmlEscape(&normalize(locale))),
+        None => String::new(),
+    }
+}
+
+/// Plural category of a number for a locale, as the CLDR rules of the most
+/// common languages, English rules by default.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::locale::plural_category;
+///
+/// assert_eq!(plural_category("en", 0.0), "other");
+/// assert_eq!(plural_category("fr", 0.0), "one");
+/// assert_eq!(plural_category("ru", 22.0), "few");
+/// assert_eq!(plural_category("ja", 1.0), "other");
+/// ```
+pub fn plural_category(locale: &str, number: f64) -> &'static str {
+    let number = number.abs();
+    let is_integer = number.fract() == 0.0;
+    let n = number as u64;
+    let (n10, n100) = (n % 10, n % 100);
+
+    match language(locale).as_str() {
+        "ja" | "ko" | "zh" | "th" | "vi" | "id" | "ms" | "tr" => "other",
+        "fr" | "pt" if n <= 1 => "one",
+        "ru" | "uk" | "be" if is_integer => match (n10, n100) {
+            (1, n100) if n100 != 11 => "one",
+            (2..=4, n100) if !(12..=14).contains(&n100) => "few",
+            _ => "many",
+        },
+        "pl" if is_integer => match (n, n10, n100) {
+            (1, _, _) => "one",
+            (_, 2..=4, n100) if !(12..=14).contains(&n100) => "few",
+            _ => "many",
+        },
+        "cs" | "sk" if is_integer => match n {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        "ar" if is_integer => match (n, n100) {
+            (0, _) => "zero",
+            (1, _) => "one",
+            (2, _) => "two",
+            (_, 3..=10) => "few",
+            (_, 11..=99) => "many",
+            _ => "other",
+        },
+        "fr" | "pt" | "ru" | "uk" | "be" | "pl" | "cs" | "sk" | "ar" => "other",
+        _ if number == 1.0 => "one",
+        _ => "other",
+    }
+}
+
+/// Group and decimal separators of the numbers for a locale, as the CLDR
+/// symbols of the most common languages, English ones by default.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::locale::number_separators;
+///
+/// assert_eq!(number_separators("en-US"), (",", "."));
+/// assert_eq!(number_separators("de"), (".", ","));
+/// assert_eq!(number_separators("fr-CA"), ("\u{202f}", ","));
+/// ```
+pub fn number_separators(locale: &str) -> (&'static str, &'static str) {
+    match language(locale).as_str() {
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro" | "vi" | "hr"
+        | "sl" | "sr" => (".", ","),
+        "fr" => ("\u{202f}", ","),
+        "ru" | "uk" | "be" | "pl" | "cs" | "sk" | "fi" | "sv" | "nb" | "no" | "hu" | "bg"
+        | "lt" | "lv" | "et" => ("\u{a0}", ","),
+        _ => (",", "."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_chain() {
+        assert_eq!(
+            fallback_chain("zh_hant_tw", None),
+            vec!["zh-Hant-TW", "zh-Hant", "zh"]
+        );
+        assert_eq!(fallback_chain("fr", Some("fr-FR")), vec!["fr", "fr-FR"]);
+        assert_eq!(fallback_chain("", Some("en")), vec!["en"]);
+    }
+
+    #[test]
+    fn test_html_attributes() {
+        assert_eq!(
+            html_attributes(Some("ar-EG")),
+            " lang=\"ar-EG\" dir=\"rtl\""
+        );
+        assert_eq!(html_attributes(Some("fr_ca")), " lang=\"fr-CA\"");
+        assert_eq!(html_attributes(None), "");
+    }
+}
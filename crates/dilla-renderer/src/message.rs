@@ -0,0 +1,516 @@
+//! Format translated messages for the `|t` filter.
+//!
+//! A subset of the ICU MessageFormat is supported:
+//!
+//! - `{name}` is replaced by the argument `name` or `@name`.
+//! - `{name, number}` formats a number with the separators of the locale,
+//!   `{name, number, integer}` rounds it and `{name, number, percent}`
+//!   multiplies it by 100.
+//! - `{name, plural, =0 {...} one {...} other {...}}` selects a plural form,
+//!   with an optional `offset:N` and `#` for the number.
+//! - `{name, select, male {...} female {...} other {...}}` selects a branch
+//!   from a string, as a gender.
+//! - `''` is an apostrophe and `'{...}'` is literal text.
+//!
+//! Plural forms follow the rules of the locale, see
+//! [`crate::locale::plural_category`]. Legacy `@name`, `%name` and `:name`
+//! placeholders are replaced after.
+//!
+//! The terms to translate are extracted from the template sources with
+//! [`extract`].
+
+use crate::locale;
+
+use minijinja::value::{Value, ValueKind};
+
+/// Prefixes of the legacy placeholders.
+const PLACEHOLDER_PREFIXES: &[char] = &['@', '%', ':'];
+
+/// Format a message with a Map of arguments for a locale.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::message::format;
+/// use minijinja::Value;
+/// use serde_json::json;
+///
+/// let message = "{count, plural, =0 {No item} one {# item} other {# items}} for @name";
+/// let args = |count: i32| Value::from_serialize(json!({"count": count, "@name": "Bob"}));
+///
+/// assert_eq!(format(message, &args(0), "en"), "No item for Bob");
+/// assert_eq!(format(message, &args(1), "en"), "1 item for Bob");
+/// assert_eq!(format(message, &args(1200), "en"), "1,200 items for Bob");
+/// ```
+pub fn format(message: &str, args: &Value, locale: &str) -> String {
+    let mut result = match message.contains('{') {
+        true => {
+            let mut parser = Parser {
+                chars: message.chars().collect(),
+                position: 0,
+                args,
+                locale,
+            };
+            // An unmatched `}` at the top level is kept as text.
+            let mut output = parser.message(None);
+            while parser.peek() == Some('}') {
+                parser.position += 1;
+                output.push('}');
+                output.push_str(&parser.message(None));
+            }
+            output
+        }
+        false => message.to_string(),
+    };
+
+    if args.kind() == ValueKind::Map {
+        if let Ok(keys) = args.try_iter() {
+            for key in keys {
+                let Some(name) = key.as_str() else {
+                    continue;
+                };
+                if name.starts_with(PLACEHOLDER_PREFIXES) {
+                    let value = args.get_item(&key).unwrap_or_default();
+                    result = result.replace(name, &value.to_string());
+                }
+            }
+        }
+    }
+
+    result
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    args: &'a Value,
+    locale: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Read up to one of the stop characters, not consumed.
+    fn read_until(&mut self, stops: &[char]) -> String {
+        let mut text = String::new();
+        while let Some(char) = self.peek() {
+            if stops.contains(&char) {
+                break;
+            }
+            text.push(char);
+            self.position += 1;
+        }
+        text.trim().to_string()
+    }
+
+    /// Format a message up to an unmatched `}`, not consumed. The number of a
+    /// plural replaces `#`.
+    fn message(&mut self, number: Option<f64>) -> String {
+        let mut output = String::new();
+        while let Some(char) = self.peek() {
+            match char {
+                '}' => break,
+                '{' => {
+                    self.position += 1;
+                    let argument = self.argument(number);
+                    output.push_str(&argument);
+                }
+                '#' if number.is_some() => {
+                    self.position += 1;
+                    output.push_str(&format_number(number.unwrap_or_default(), "", self.locale));
+                }
+                '\'' => {
+                    self.position += 1;
+                    output.push_str(&self.quoted(number.is_some()));
+                }
+                _ => {
+                    self.position += 1;
+                    output.push(char);
+                }
+            }
+        }
+        output
+    }
+
+    /// Text after an apostrophe: an escaped apostrophe, a quoted literal or
+    /// the apostrophe itself.
+    fn quoted(&mut self, in_plural: bool) -> String {
+        match self.peek() {
+            Some('\'') => {
+                self.position += 1;
+                "'".to_string()
+            }
+            Some('{' | '}') => self.literal(),
+            Some('#') if in_plural => self.literal(),
+            _ => "'".to_string(),
+        }
+    }
+
+    fn literal(&mut self) -> String {
+        let mut text = String::new();
+        while let Some(char) = self.peek() {
+            self.position += 1;
+            if char == '\'' {
+                if self.peek() == Some('\'') {
+                    self.position += 1;
+                    text.push('\'');
+                    continue;
+                }
+                break;
+            }
+            text.push(char);
+        }
+        text
+    }
+
+    /// Format an argument after its `{`, up to its `}` consumed.
+    fn argument(&mut self, number: Option<f64>) -> String {
+        let start = self.position - 1;
+        let name = self.read_until(&[',', '}']);
+        let value = self.arg(&name);
+
+        if self.peek() != Some(',') {
+            self.position += 1;
+            return match value {
+                Some(value) => value.to_string(),
+                None => self.raw(start),
+            };
+        }
+        self.position += 1;
+        let kind = self.read_until(&[',', '}']);
+        if self.peek() == Some(',') {
+            self.position += 1;
+        }
+
+        let output = match kind.as_str() {
+            "number" => {
+                let style = self.read_until(&['}']);
+                match value.as_ref().and_then(to_number) {
+                    Some(number) => Some(format_number(number, &style, self.locale)),
+                    None => value.map(|value| value.to_string()),
+                }
+            }
+            "plural" => {
+                let value = value.as_ref().and_then(to_number);
+                let offset = self.offset();
+                let options = self.options(value.map(|value| value - offset).or(number));
+                value.and_then(|value| {
+                    choose(
+                        &options,
+                        &format!("={}", format_number(value, "raw", self.locale)),
+                    )
+                    .or_else(|| {
+                        choose(
+                            &options,
+                            locale::plural_category(self.locale, value - offset),
+                        )
+                    })
+                    .or_else(|| choose(&options, "other"))
+                })
+            }
+            "select" => {
+                let options = self.options(number);
+                value.and_then(|value| {
+                    choose(&options, &value.to_string()).or_else(|| choose(&options, "other"))
+                })
+            }
+            _ => {
+                self.skip_argument();
+                None
+            }
+        };
+        self.position += 1;
+
+        output.unwrap_or_else(|| self.raw(start))
+    }
+
+    /// Read an optional `offset:N` of a plural.
+    fn offset(&mut self) -> f64 {
+        self.skip_whitespace();
+        let rest: String = self
+            .chars
+            .get(self.position..)
+            .unwrap_or_default()
+            .iter()
+            .take(7)
+            .collect();
+        if rest != "offset:" {
+            return 0.0;
+        }
+        self.position += 7;
+        self.skip_whitespace();
+        let offset = self.read_until(&[' ', '\t', '\n', '{', '}']);
+        offset.parse().unwrap_or_default()
+    }
+
+    /// Read the `selector {message}` options up to the argument `}`, not
+    /// consumed.
+    fn options(&mut self, number: Option<f64>) -> Vec<(String, String)> {
+        let mut options = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let selector = self.read_until(&['{', '}', ' ', '\t', '\n']);
+            self.skip_whitespace();
+            if selector.is_empty() || self.peek() != Some('{') {
+                break;
+            }
+            self.position += 1;
+            let message = self.message(number);
+            self.position += 1;
+            options.push((selector, message));
+        }
+        self.skip_argument();
+        options
+    }
+
+    /// Skip to the `}` closing the argument, not consumed.
+    fn skip_argument(&mut self) {
+        let mut depth = 0;
+        while let Some(char) = self.peek() {
+            match char {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => (),
+            }
+            self.position += 1;
+        }
+    }
+
+    /// The argument source, kept when it can not be formatted.
+    fn raw(&self, start: usize) -> String {
+        let end = self.position.min(self.chars.len());
+        self.chars[start..end].iter().collect()
+    }
+
+    /// Get an argument by name, or with the legacy `@` prefix.
+    fn arg(&self, name: &str) -> Option<Value> {
+        if self.args.kind() != ValueKind::Map || name.is_empty() {
+            return None;
+        }
+        [name.to_string(), format!("@{name}")]
+            .iter()
+            .map(|key| {
+                self.args
+                    .get_item(&Value::from(key.as_str()))
+                    .unwrap_or_default()
+            })
+            .find(|value| !value.is_undefined())
+    }
+}
+
+fn choose(options: &[(String, String)], selector: &str) -> Option<String> {
+    options
+        .iter()
+        .find(|(option, _)| option == selector)
+        .map(|(_, message)| message.clone())
+}
+
+fn to_number(value: &Value) -> Option<f64> {
+    match value.kind() {
+        ValueKind::Number => f64::try_from(value.clone()).ok(),
+        ValueKind::String => value.as_str()?.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Format a number with a style: `integer`, `percent`, `raw` without grouping
+/// or empty for the default with up to 3 fraction digits. Separators are the
+/// ones of the locale, except for `raw`.
+fn format_number(number: f64, style: &str, locale: &str) -> String {
+    let (number, suffix, digits) = match style {
+        "integer" => (number.round(), "", 0),
+        "percent" => ((number * 100.0).round(), "%", 0),
+        _ => (number, "", 3),
+    };
+    let formatted = format!("{:.*}", digits, number);
+    let formatted = match formatted.contains('.') {
+        true => formatted.trim_end_matches('0').trim_end_matches('.'),
+        false => &formatted,
+    };
+    let formatted = match formatted {
+        "-0" => "0",
+        formatted => formatted,
+    };
+    if style == "raw" {
+        return formatted.to_string();
+    }
+
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", formatted),
+    };
+    let (group, decimal) = locale::number_separators(locale);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, format!("{decimal}{fraction}")),
+        None => (unsigned, String::new()),
+    };
+    let mut grouped = String::new();
+    for (position, char) in integer.chars().enumerate() {
+        if position > 0 && (integer.len() - position) % 3 == 0 {
+            grouped.push_str(group);
+        }
+        grouped.push(char);
+    }
+
+    format!("{sign}{grouped}{fraction}{suffix}")
+}
+
+/// Extract the literal terms of the `'...'|t` filters in a template source,
+/// in order of appearance. Only the `{{ }}` and `{% %}` blocks are read.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::message::extract;
+///
+/// let source = "<p>Don't {{ 'Hello @name!'|t({'@name': name}) }}</p>{{ 'Raw'|upper }}";
+///
+/// assert_eq!(extract(source), vec!["Hello @name!"]);
+/// ```
+pub fn extract(source: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].chars().nth(1) {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            _ => {
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        let block = &rest[start + 2..];
+        rest = &block[extract_block(block, end, &mut terms)..];
+    }
+    terms
+}
+
+/// Collect the terms of a block up to its end tag, outside of the strings.
+/// Returns the length read.
+fn extract_block(block: &str, end: &str, terms: &mut Vec<String>) -> usize {
+    let chars: Vec<(usize, char)> = block.char_indices().collect();
+    let mut index = 0;
+    while let Some(&(position, char)) = chars.get(index) {
+        if block[position..].starts_with(end) {
+            return position + end.len();
+        }
+        index += 1;
+        if char != '\'' && char != '"' {
+            continue;
+        }
+        let mut term = String::new();
+        while let Some(&(_, next)) = chars.get(index) {
+            index += 1;
+            match next {
+                '\\' => {
+                    if let Some(&(_, escaped)) = chars.get(index) {
+                        index += 1;
+                        term.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            escaped => escaped,
+                        });
+                    }
+                }
+                next if next == char => break,
+                next => term.push(next),
+            }
+        }
+        let after: String = chars
+            .get(index..)
+            .unwrap_or_default()
+            .iter()
+            .map(|(_, char)| char)
+            .collect();
+        let Some(filter) = after.trim_start().strip_prefix('|') else {
+            continue;
+        };
+        let filter = filter.trim_start();
+        let is_t = filter.starts_with('t')
+            && !filter
+                .chars()
+                .nth(1)
+                .is_some_and(|char| char.is_alphanumeric() || char == '_');
+        if is_t && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    block.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+
+    #[test]
+    fn test_format_arguments() {
+        let args = context! { name => "Bob", num => 1234.5678, ratio => 0.256 };
+
+        assert_eq!(format("Hello {name}!", &args, "en"), "Hello Bob!");
+        assert_eq!(format("{num, number}", &args, "en"), "1,234.568");
+        assert_eq!(format("{num, number, integer}", &args, "en"), "1,235");
+        assert_eq!(format("{ratio, number, percent}", &args, "en"), "26%");
+        assert_eq!(
+            format("{missing} {name, unknown, x}", &args, "en"),
+            "{missing} {name, unknown, x}"
+        );
+        assert_eq!(format("Say \"{name}\"", &args, "en"), "Say \"Bob\"");
+        assert_eq!(format("{num, number}", &args, "de"), "1.234,568");
+        assert_eq!(format("{num, number}", &args, "fr"), "1\u{202f}234,568");
+        assert_eq!(format("a {name} b } c", &args, "en"), "a Bob b } c");
+    }
+
+    #[test]
+    fn test_format_plural_select() {
+        let message = "{gender, select, female {She} male {He} other {They}} invited \
+            {guests, plural, offset:1 =0 {nobody} =1 {{host}} one {{host} and # other} \
+            other {{host} and # others}}.";
+        let args = |gender: &str, guests: i32| context! { gender, guests, host => "Ana" };
+
+        assert_eq!(
+            format(message, &args("female", 0), "en"),
+            "She invited nobody."
+        );
+        assert_eq!(format(message, &args("male", 1), "en"), "He invited Ana.");
+        assert_eq!(
+            format(message, &args("x", 2), "en"),
+            "They invited Ana and 1 other."
+        );
+        assert_eq!(
+            format(message, &args("x", 5), "en"),
+            "They invited Ana and 4 others."
+        );
+    }
+
+    #[test]
+    fn test_format_quotes() {
+        let args = context! { count => 2 };
+
+        assert_eq!(format("l'article", &args, "en"), "l'article");
+        assert_eq!(format("l''{count}", &args, "en"), "l'2");
+        assert_eq!(format("'{count}' is {count}", &args, "en"), "{count} is 2");
+        assert_eq!(format("{count, plural, other {'#' #}}", &args, "en"), "# 2");
+        assert_eq!(
+            format("Unclosed {count, plural, other {#", &args, "en"),
+            "Unclosed 2"
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let source = "{% set label = \"It's\"|t %}{{ 'Say \\'hi\\''|t }} {{ 'x'|trim }}\n\
+            {{ 'a' ~ 'Tail' | t({'n': '}}'}) }} {{ 'Say \\'hi\\''|t }} {{ 'Open'|t";
+
+        assert_eq!(extract(source), vec!["It's", "Say 'hi'", "Tail", "Open"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Handle render options set from the API.
+//!
+//! Options are not part of the payload, they are set by the caller of the
+//! render and stored in the MiniJinja environment to be available for nested
+//! renders.
+
+use crate::{limits::RenderLimits, policy::SecurityPolicy};
+
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Name of the environment global to store the options.
+pub(crate) const KEY_OPTIONS: &str = "_options";
+
+/// Options to alter the render.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Content-Security-Policy nonce added to every generated `<script>` and
+    /// `<style>`.
+    pub nonce: Option<String>,
+    /// Render `@local_variables` in a class scoped stylesheet instead of an
+    /// inline `style` attribute.
+    pub scoped_local_variables: bool,
+    /// Generate resource hints for the libraries in the `attached` part and
+    /// the `hints` field of the json output.
+    pub resource_hints: bool,
+    /// Local root directory of the libraries files, used by the `full_inline`
+    /// output to inline the CSS and JS content.
+    pub asset_root: Option<String>,
+    /// Build the DOM tree while rendering, set for the `dom` output.
+    pub dom: bool,
+    /// Remove the formatting whitespace of the HTML.
+    pub minify: bool,
+    /// Indent the HTML and the json, takes precedence over `minify`.
+    pub pretty: bool,
+    /// Record the JSON pointer and the body range of every renderable in the
+    /// `source_map` field of the json output. The body is then not formatted
+    /// by `minify` or `pretty` to keep the ranges valid.
+    pub source_map: bool,
+    /// Wrap each renderable in `<!-- dilla:start -->` and `<!-- dilla:end -->`
+    /// comments with the component, variant and template used.
+    pub debug: bool,
+    /// Record the render time of each component in the `timing` field of the
+    /// json output, needs the `profiling` feature.
+    pub profiling: bool,
+    /// Limits of the render for untrusted payloads, no limit by default.
+    pub limits: RenderLimits,
+    /// Renderable types, element tags and attributes allowed in the payload,
+    /// everything by default.
+    pub policy: SecurityPolicy,
+    /// Seed of the `random()` template function, derived from the payload
+    /// when not set so the same payload renders the same output.
+    pub seed: Option<u64>,
+    /// Locale of the render, takes precedence over the payload **@lang**.
+    pub locale: Option<String>,
+    /// Last locale of the translation fallback chain.
+    pub fallback_locale: Option<String>,
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        RenderOptions::default()
+    }
+
+    /// Set the Content-Security-Policy nonce.
+    pub fn with_nonce(mut self, nonce: &str) -> Self {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    /// Set rendering of `@local_variables` as a stylesheet.
+    pub fn with_scoped_local_variables(mut self, scoped: bool) -> Self {
+        self.scoped_local_variables = scoped;
+        self
+    }
+
+    /// Set generation of resource hints.
+    pub fn with_resource_hints(mut self, hints: bool) -> Self {
+        self.resource_hints = hints;
+        self
+    }
+
+    /// Set the local root directory of the libraries files.
+    pub fn with_asset_root(mut self, root: &str) -> Self {
+        self.asset_root = Some(root.to_string());
+        self
+    }
+
+    /// Set building of the DOM tree.
+    pub fn with_dom(mut self, dom: bool) -> Self {
+        self.dom = dom;
+        self
+    }
+
+    /// Set minification of the HTML.
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Set pretty print of the HTML and the json.
+    pub fn with_pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Set recording of the source map.
+    pub fn with_source_map(mut self, source_map: bool) -> Self {
+        self.source_map = source_map;
+        self
+    }
+
+    /// Set the debug annotations.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Set recording of the render timing, needs the `profiling` feature.
+    #[cfg(feature = "profiling")]
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    /// Set the limits of the render.
+    pub fn with_limits(mut self, limits: RenderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the security policy of the payload.
+    pub fn with_policy(mut self, policy: SecurityPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the seed of the `random()` template function.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the locale of the render.
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_string());
+        self
+    }
+
+    /// Set the last locale of the translation fallback chain.
+    pub fn with_fallback_locale(mut self, locale: &str) -> Self {
+        self.fallback_locale = Some(locale.to_string());
+        self
+    }
+
+    /// Store the options in the environment.
+    pub(crate) fn add_to_env(&self, env: &mut Environment) {
+        env.add_global(KEY_OPTIONS, minijinja::Value::from_object(self.clone()));
+    }
+
+    /// Get the options stored in the environment or default.
+    pub(crate) fn from_env(env: &Environment) -> Self {
+        env.empty_state()
+            .lookup(KEY_OPTIONS)
+            .and_then(|value| value.downcast_object_ref::<RenderOptions>().cloned())
+            .unwrap_or_default()
+    }
+
+    /// Get the nonce as an HTML attribute string.
+    pub(crate) fn nonce_attribute(&self) -> String {
+        match &self.nonce {
+            Some(nonce) => format!(" nonce=\"{}\"", minijinja::HtmlEscape(nonce)),
+            None => String::new(),
+        }
+    }
+}
+
+impl fmt::Display for RenderOptions {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl minijinja::value::Object for RenderOptions {}
@@ -0,0 +1,194 @@
+//! Output formats of the render.
+//!
+//! Built-in formats are listed in [`OutputFormat`], downstream crates can
+//! register their own [`Formatter`] with [`register_formatter`] and use it by
+//! name as any other format.
+
+use crate::{error::Error, options::RenderOptions, renderer::RendererWrapper};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Format of the render output.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// A json response with every rendered parts.
+    #[default]
+    Json,
+    /// A json response as `json` with each top-level item HTML in
+    /// `fragments` instead of `body`.
+    Fragments,
+    /// Whole HTML page wrapped in `<html>` tag.
+    Full,
+    /// Whole HTML page with local CSS and JS libraries inlined.
+    FullInline,
+    /// Whole HTML page for email clients.
+    Email,
+    /// A json DOM tree of the `attached` and `body` parts.
+    Dom,
+    /// Only HTML body without head, styles and libraries, for test purpose.
+    Test,
+    /// All HTML parts not wrapped in `<html>` tag, for test purpose.
+    TestFull,
+    /// Display on logs, mostly debug and internal test purpose.
+    Logs,
+    /// A formatter registered with [`register_formatter`].
+    Custom(String),
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Fragments => "fragments",
+            OutputFormat::Full => "full",
+            OutputFormat::FullInline => "full_inline",
+            OutputFormat::Email => "email",
+            OutputFormat::Dom => "dom",
+            OutputFormat::Test => "_test",
+            OutputFormat::TestFull => "_test_full",
+            OutputFormat::Logs => "_logs",
+            OutputFormat::Custom(name) => name,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    /// Parse a built-in or registered format name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::output::OutputFormat;
+    ///
+    /// assert_eq!("full".parse::<OutputFormat>().unwrap(), OutputFormat::Full);
+    /// assert!("ful".parse::<OutputFormat>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match builtin(name) {
+            Some(format) => Ok(format),
+            None if formatter(name).is_some() => Ok(OutputFormat::Custom(name.to_string())),
+            None => Err(Error::UnknownOutput(name.to_string())),
+        }
+    }
+}
+
+/// Get a built-in format by name.
+fn builtin(name: &str) -> Option<OutputFormat> {
+    match name {
+        "json" => Some(OutputFormat::Json),
+        "fragments" => Some(OutputFormat::Fragments),
+        "full" => Some(OutputFormat::Full),
+        "full_inline" => Some(OutputFormat::FullInline),
+        "email" => Some(OutputFormat::Email),
+        "dom" => Some(OutputFormat::Dom),
+        "_test" => Some(OutputFormat::Test),
+        "_test_full" => Some(OutputFormat::TestFull),
+        "_logs" => Some(OutputFormat::Logs),
+        _ => None,
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Format the rendered parts to a custom output.
+///
+/// # Examples
+///
+/// ```
+/// use dilla_renderer::options::RenderOptions;
+/// use dilla_renderer::output::{register_formatter, Formatter};
+/// use dilla_renderer::renderer::RendererWrapper;
+///
+/// struct BodyOnly;
+///
+/// impl Formatter for BodyOnly {
+///     fn format(&self, output: &RendererWrapper, _options: &RenderOptions) -> String {
+///         output.body.trim().to_string()
+///     }
+/// }
+///
+/// register_formatter("body_only", BodyOnly).unwrap();
+///
+/// let payload = r#"{"@element": "p", "@content": "Hello"}"#;
+/// let result = dilla_renderer::render(payload, "body_only").unwrap();
+/// assert_eq!(result, "<p>Hello</p>");
+/// ```
+pub trait Formatter: Send + Sync {
+    fn format(&self, output: &RendererWrapper, options: &RenderOptions) -> String;
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn Formatter>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Register a custom formatter by name.
+///
+/// # Errors
+///
+/// [`Error::ReservedOutput`] if the name is a built-in format.
+pub fn register_formatter(name: &str, formatter: impl Formatter + 'static) -> Result<(), Error> {
+    if builtin(name).is_some() {
+        return Err(Error::ReservedOutput(name.to_string()));
+    }
+    if let Ok(mut registry) = registry().write() {
+        registry.insert(name.to_string(), Arc::new(formatter));
+    }
+    Ok(())
+}
+
+/// Get a registered formatter by name.
+pub(crate) fn formatter(name: &str) -> Option<Arc<dyn Formatter>> {
+    registry().read().ok()?.get(name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+
+    impl Formatter for Empty {
+        fn format(&self, _output: &RendererWrapper, _options: &RenderOptions) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn test_register_reserved_name() {
+        for name in ["json", "full", "_test"] {
+            assert!(matches!(
+                register_formatter(name, Empty),
+                Err(Error::ReservedOutput(reserved)) if reserved == name
+            ));
+        }
+        assert_eq!("full".parse::<OutputFormat>().unwrap(), OutputFormat::Full);
+
+        assert!(register_formatter("empty", Empty).is_ok());
+        assert_eq!(
+            "empty".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Custom("empty".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unregistered_custom_output() {
+        let output = OutputFormat::Custom("unregistered".to_string());
+
+        assert!(matches!(
+            crate::render_with_options("\"text\"", output, RenderOptions::new()),
+            Err(Error::UnknownOutput(name)) if name == "unregistered"
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+//! Restrict what a payload can render.
+//!
+//! The security policy is set with the render option `policy` for payloads
+//! from untrusted sources. Renderable types and element tags not allowed are
+//! not rendered, attributes not allowed are removed from elements and
+//! components. Everything is allowed by default, [`SecurityPolicy::strict`]
+//! is a preset for user generated content.
+
+use crate::renderable::RenderableType;
+
+use serde::{Deserialize, Serialize};
+
+/// Attributes with an URL value, checked with `safe_urls`.
+const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "formaction",
+    "href",
+    "poster",
+    "src",
+    "xlink:href",
+];
+
+/// URL schemes able to run a script.
+const UNSAFE_SCHEMES: &[&str] = &["javascript:", "vbscript:"];
+
+/// Allow and deny lists for the payload renderables.
+///
+/// An allow list set to `None` allows everything not denied. Attribute names
+/// can end with `*` to match a prefix, as `on*` for the event handlers.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityPolicy {
+    pub allowed_types: Option<Vec<RenderableType>>,
+    pub denied_types: Vec<RenderableType>,
+    pub allowed_tags: Option<Vec<String>>,
+    pub denied_tags: Vec<String>,
+    pub allowed_attributes: Option<Vec<String>>,
+    pub denied_attributes: Vec<String>,
+    /// Remove URL attributes with a `javascript:` or `vbscript:` scheme.
+    pub safe_urls: bool,
+}
+
+impl SecurityPolicy {
+    pub fn new() -> Self {
+        SecurityPolicy::default()
+    }
+
+    /// Policy for user generated content: no `@template`, no tags running
+    /// scripts or loading documents, no event handlers and safe URLs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dilla_renderer::policy::SecurityPolicy;
+    /// use dilla_renderer::renderable::RenderableType;
+    ///
+    /// let policy = SecurityPolicy::strict();
+    ///
+    /// assert!(!policy.allows_type(&RenderableType::Template));
+    /// assert!(!policy.allows_tag("SCRIPT"));
+    /// assert!(!policy.allows_attribute("onclick", "alert(1)"));
+    /// assert!(!policy.allows_attribute("href", " JavaScript:alert(1)"));
+    /// assert!(policy.allows_attribute("href", "/javascript:"));
+    /// ```
+    pub fn strict() -> Self {
+        SecurityPolicy::new()
+            .with_denied_types(vec![RenderableType::Template])
+            .with_denied_tags(&[
+                "base", "embed", "frame", "frameset", "iframe", "link", "meta", "object", "script",
+                "style",
+            ])
+            .with_denied_attributes(&["on*", "srcdoc"])
+            .with_safe_urls(true)
+    }
+
+    pub fn with_allowed_types(mut self, types: Vec<RenderableType>) -> Self {
+        self.allowed_types = Some(types);
+        self
+    }
+
+    pub fn with_denied_types(mut self, types: Vec<RenderableType>) -> Self {
+        self.denied_types = types;
+        self
+    }
+
+    pub fn with_allowed_tags(mut self, tags: &[&str]) -> Self {
+        self.allowed_tags = Some(to_strings(tags));
+        self
+    }
+
+    pub fn with_denied_tags(mut self, tags: &[&str]) -> Self {
+        self.denied_tags = to_strings(tags);
+        self
+    }
+
+    pub fn with_allowed_attributes(mut self, names: &[&str]) -> Self {
+        self.allowed_attributes = Some(to_strings(names));
+        self
+    }
+
+    pub fn with_denied_attributes(mut self, names: &[&str]) -> Self {
+        self.denied_attributes = to_strings(names);
+        self
+    }
+
+    pub fn with_safe_urls(mut self, safe_urls: bool) -> Self {
+        self.safe_urls = safe_urls;
+        self
+    }
+
+    /// Check a renderable type can be rendered.
+    pub fn allows_type(&self, renderable_type: &RenderableType) -> bool {
+        if self.denied_types.contains(renderable_type) {
+            return false;
+        }
+        match &self.allowed_types {
+            Some(allowed) => allowed.contains(renderable_type),
+            None => true,
+        }
+    }
+
+    /// Check an element tag can be rendered, case insensitive.
+    pub fn allows_tag(&self, tag: &str) -> bool {
+        let is_listed = |tags: &[String]| tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        if is_listed(&self.denied_tags) {
+            return false;
+        }
+        match &self.allowed_tags {
+            Some(allowed) => is_listed(allowed),
+            None => true,
+        }
+    }
+
+    /// Check an attribute can be rendered with this value.
+    pub fn allows_attribute(&self, name: &str, value: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        if matches_any(&self.denied_attributes, &name) {
+            return false;
+        }
+        if let Some(allowed) = &self.allowed_attributes {
+            if !matches_any(allowed, &name) {
+                return false;
+            }
+        }
+        !(self.safe_urls && URL_ATTRIBUTES.contains(&name.as_str()) && is_unsafe_url(value))
+    }
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Check a lowercase name match a list of names or `prefix*` patterns.
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        }
+    })
+}
+
+/// Check the scheme of an URL, ignoring whitespace and control characters as
+/// browsers do.
+fn is_unsafe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|char| !char.is_whitespace() && !char.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    UNSAFE_SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow_lists() {
+        let policy = SecurityPolicy::new()
+            .with_allowed_types(vec![RenderableType::Element])
+            .with_allowed_tags(&["p", "a"])
+            .with_allowed_attributes(&["href", "data-*"]);
+
+        assert!(policy.allows_type(&RenderableType::Element));
+        assert!(!policy.allows_type(&RenderableType::Component));
+        assert!(policy.allows_tag("A"));
+        assert!(!policy.allows_tag("div"));
+        assert!(policy.allows_attribute("data-id", "1"));
+        assert!(policy.allows_attribute("href", "javascript:alert(1)"));
+        assert!(!policy.allows_attribute("title", "a"));
+    }
+
+    #[test]
+    fn test_unsafe_url() {
+        assert!(is_unsafe_url("java\tscript:alert(1)"));
+        assert!(is_unsafe_url("\u{1}VBScript:msgbox"));
+        assert!(!is_unsafe_url("https://example.com/javascript:"));
+        assert!(!is_unsafe_url("#javascript:"));
+    }
+}
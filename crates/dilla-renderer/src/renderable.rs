@@ -2,8 +2,12 @@
 
 use crate::{
     attribute::{Attribute, KEY_ATTRIBUTES},
+    dom::{self, Node},
+    limits,
+    options::RenderOptions,
     renderer::Renderer,
-    DEFINITION, KEY_PREFIX,
+    source_map::{self, KEY_SOURCE},
+    timing, DEFINITION, KEY_PREFIX, LIBRARIES_INTEGRITY,
 };
 
 use minijinja::{context, value::ValueKind, Environment};
@@ -18,11 +22,20 @@ pub const SEP_COMPONENT_VARIANT: &str = ".";
 /// Element renderable key name.
 pub const KEY_ELEMENT: &str = "element";
 const KEY_ELEMENT_CONTENT: &str = "content";
-const VOID_ELEMENTS: &[&str] = &[
+pub(crate) const VOID_ELEMENTS: &[&str] = &[
     "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
     "wbr", "use",
 ];
 
+/// Library script property to set the placement, `head` or default `body`.
+pub const KEY_SCRIPT_PLACEMENT: &str = "placement";
+/// Library script property to set a `nomodule` fallback for a module script.
+pub const KEY_SCRIPT_FALLBACK: &str = "fallback";
+/// Inline library script property for the code.
+pub const KEY_SCRIPT_CONTENT: &str = "script";
+/// Inline library script property for the initialization options.
+pub const KEY_SCRIPT_OPTIONS: &str = "options";
+
 /// Name of the template property.
 pub const KEY_TEMPLATE: &str = "template";
 
@@ -53,6 +66,15 @@ pub struct Renderable {
     element_tag: String,
     // Element: content property.
     element_content: String,
+    // Render options from the environment.
+    #[serde(skip)]
+    options: RenderOptions,
+    // DOM nodes of the output, with option `dom`.
+    #[serde(skip)]
+    nodes: Vec<Node>,
+    // Element: DOM nodes of the content, with option `dom`.
+    #[serde(skip)]
+    element_children: Vec<Node>,
 }
 
 impl Renderable {
@@ -69,6 +91,9 @@ impl Renderable {
             template_source: String::new(),
             element_tag: String::new(),
             element_content: String::new(),
+            options: RenderOptions::default(),
+            nodes: Vec::new(),
+            element_children: Vec::new(),
         }
     }
 
@@ -80,6 +105,11 @@ impl Renderable {
         self.output.clone() as _
     }
 
+    /// DOM nodes of the output, built only with option `dom`.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
     pub fn build(&mut self) -> &mut Self {
         self.set_type_from_data();
 
@@ -94,6 +124,7 @@ impl Renderable {
     }
 
     pub fn build_with_env(&mut self, env: &mut Environment, ctx: minijinja::Value) -> &mut Self {
+        self.options = RenderOptions::from_env(env);
         self.set_type_from_data();
 
         if self.renderable_type == RenderableType::Element {
@@ -102,10 +133,74 @@ impl Renderable {
 
         self.set_renderable_values();
         self.render_with_env(env, ctx);
+        if !limits::check_output_size(self.output.len()) {
+            self.output.clear();
+        }
+
+        if self.options.dom {
+            self.set_nodes();
+        }
+        if self.options.debug {
+            self.wrap_debug();
+        }
+        if self.options.source_map {
+            self.wrap_source();
+        }
 
         self
     }
 
+    /// Wrap the output in comments to trace the markup back to the template.
+    fn wrap_debug(&mut self) {
+        let annotations = match self.renderable_type {
+            RenderableType::Component => {
+                let mut annotations = vec![format!("component={}", self.component_name)];
+                let key_variant = KEY_PREFIX.to_string() + KEY_COMPONENT_VARIANT;
+                if let Some(variant) = self.data.get(&key_variant).and_then(|v| v.as_str()) {
+                    annotations.push(format!("variant={}", variant));
+                }
+                annotations.push(format!("template={}", self.template_name()));
+                annotations
+            }
+            RenderableType::Element => vec![format!("element={}", self.element_tag)],
+            RenderableType::Template => vec!["template=inline".to_string()],
+            RenderableType::Unknown => return,
+        };
+        let annotations: Vec<String> = annotations.iter().map(|a| comment_safe(a)).collect();
+
+        self.output = format!(
+            "<!-- dilla:start {} -->{}<!-- dilla:end -->",
+            annotations.join(" "),
+            self.output
+        );
+    }
+
+    /// Wrap the output in the markers of the payload pointer.
+    fn wrap_source(&mut self) {
+        let Some(pointer) = self.data.get(KEY_SOURCE).and_then(|v| v.as_str()) else {
+            return;
+        };
+        let (renderable_type, name) = match self.renderable_type {
+            RenderableType::Component => ("component", self.component_name.as_str()),
+            RenderableType::Element => ("element", self.element_tag.as_str()),
+            RenderableType::Template => ("template", ""),
+            RenderableType::Unknown => return,
+        };
+        self.output = source_map::wrap(&self.output, pointer, renderable_type, name);
+    }
+
+    /// Elements are built as node, other renderables output is parsed.
+    fn set_nodes(&mut self) {
+        self.nodes = match self.renderable_type {
+            RenderableType::Element => vec![Node::Element {
+                tag: self.element_tag.clone(),
+                attributes: self.attributes.to_map(),
+                children: std::mem::take(&mut self.element_children),
+            }],
+            _ => dom::parse(&source_map::strip(&self.output)),
+        };
+    }
+
     fn render_with_env(&mut self, env: &mut Environment, ctx: minijinja::Value) {
         if self.renderable_type == RenderableType::Template {
             self.output = self.render_template(env, ctx)
@@ -144,6 +239,7 @@ impl Renderable {
     }
 
     fn render_component(&mut self, env: &mut Environment, ctx: minijinja::Value) -> String {
+        let _span = timing::span(&self.component_name);
         // Merge context values to have translation and fields.
         // @todo have fields directly as minijinja::value?
         let ctx_fields = minijinja::Value::from_serialize(&self.fields);
@@ -156,16 +252,7 @@ impl Renderable {
         //     minijinja::value::Value::from_object(component.attributes()),
         // );
 
-        // Name of the template is from macro minijinja_embed::embed_templates!, it store path and name with extension.
-        let mut template_name = format!(
-            "{}/{}.jinja",
-            self.component_template.as_str(),
-            self.component_template.as_str()
-        );
-        if DEFINITION.design_system == "test" {
-            template_name = format!("{}.jinja", self.component_template.as_str());
-        }
-
+        let template_name = self.template_name();
         let template = env.get_template(&template_name);
 
         if template.is_err() {
@@ -177,20 +264,41 @@ impl Renderable {
             );
         }
 
-        template.unwrap().render(&ctx).unwrap()
+        template.unwrap().render(&ctx).unwrap_or_else(|error| {
+            limits::template_error(error);
+            String::new()
+        })
+    }
+
+    /// Name of the component template.
+    fn template_name(&self) -> String {
+        // Name of the template is from macro minijinja_embed::embed_templates!, it store path and name with extension.
+        if DEFINITION.design_system == "test" {
+            return format!("{}.jinja", self.component_template.as_str());
+        }
+
+        format!(
+            "{}/{}.jinja",
+            self.component_template.as_str(),
+            self.component_template.as_str()
+        )
     }
 
     fn render_template(&mut self, env: &mut Environment, ctx: minijinja::Value) -> String {
+        let _span = timing::span("@template");
         let mut env: Environment = env.clone();
 
-        env.add_template("inline", &self.template_source).unwrap();
-        let template = env.get_template("inline").unwrap();
-
         // Merge context values to have translation and fields.
         let ctx_fields = minijinja::Value::from_serialize(&self.data);
         let ctx = context! { ..ctx, ..ctx_fields };
 
-        let output = template.render(&ctx).unwrap();
+        let output = env
+            .add_template("inline", &self.template_source)
+            .and_then(|_| env.get_template("inline")?.render(&ctx))
+            .unwrap_or_else(|error| {
+                limits::template_error(error);
+                String::new()
+            });
 
         // @todo do we need to?
         env.remove_template("inline");
@@ -212,6 +320,10 @@ impl Renderable {
             (_, Some(_), _) => RenderableType::Component,
             (_, _, Some(_)) => RenderableType::Template,
             _ => RenderableType::Unknown,
+        };
+
+        if !self.options.policy.allows_type(&self.renderable_type) {
+            self.renderable_type = RenderableType::Unknown;
         }
     }
 
@@ -256,6 +368,18 @@ impl Renderable {
         if let Some(value) = self.data.get(&format!("{KEY_PREFIX}{KEY_ELEMENT}")) {
             self.element_tag = value.as_str().unwrap_or_default().to_string();
         }
+
+        // A tag with other characters could carry attributes past the policy.
+        let is_valid = self
+            .element_tag
+            .starts_with(|c: char| c.is_ascii_alphabetic())
+            && self
+                .element_tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !is_valid || !self.options.policy.allows_tag(&self.element_tag) {
+            self.renderable_type = RenderableType::Unknown;
+        }
     }
 
     fn set_element_content(&mut self, env: &mut Environment) {
@@ -265,10 +389,11 @@ impl Renderable {
         if self.data.contains_key(&content_key) {
             let content_element = self.data.get(&content_key).unwrap();
 
-            let mut single_renderer = Renderer::new();
+            let mut single_renderer = Renderer::with_options(self.options.clone());
             single_renderer.do_render(&[content_element.to_owned()], env);
 
             self.element_content = single_renderer.output.body;
+            self.element_children = single_renderer.dom;
         }
     }
 
@@ -295,8 +420,9 @@ impl Renderable {
 
         let mut obj_attributes = Attribute::new();
 
-        obj_attributes.build_scoped(&self.data);
+        obj_attributes.build_scoped_with_options(&self.data, &self.options);
         obj_attributes.add_attr_from_serde(attributes);
+        obj_attributes.retain_allowed(&self.options.policy);
 
         self.attributes = obj_attributes;
     }
@@ -310,6 +436,7 @@ impl Renderable {
         defined_attributes.add_attr("href", vec![href]);
 
         defined_attributes.add_attr_from_serde(attrs);
+        add_integrity(&mut defined_attributes, href);
 
         let mut renderable = Renderable::default();
         renderable.set_type(RenderableType::Element);
@@ -327,6 +454,9 @@ impl Renderable {
         defined_attributes.add_attr("src", vec![src]);
 
         defined_attributes.add_attr_from_serde(attrs);
+        defined_attributes.remove_attr_by_name(KEY_SCRIPT_PLACEMENT);
+        defined_attributes.remove_attr_by_name(KEY_SCRIPT_FALLBACK);
+        add_integrity(&mut defined_attributes, src);
 
         let mut renderable = Renderable::default();
         renderable.set_type(RenderableType::Element);
@@ -334,6 +464,55 @@ impl Renderable {
         renderable.set_attributes(defined_attributes);
         renderable.render();
 
+        // Module script can have a legacy script for browsers without support.
+        if let Some(fallback) = attrs.get(KEY_SCRIPT_FALLBACK).and_then(|v| v.as_str()) {
+            let mut fallback_attrs = json!({ "nomodule": "true" });
+            if let Some(nonce) = attrs.get("nonce") {
+                fallback_attrs["nonce"] = nonce.to_owned();
+            }
+            let fallback_script = Renderable::script(fallback, &fallback_attrs);
+            renderable
+                .output
+                .push_str(&format!("\n{}", fallback_script.to_html_string()));
+        }
+
+        renderable
+    }
+
+    /// Shortcut to generate an inline `script` HTML tag with Element.
+    ///
+    /// The code is wrapped in a function to receive the initialization
+    /// `options` as a JSON object.
+    pub fn inline_script(attrs: &serde_json::Value) -> Self {
+        let content = attrs
+            .get(KEY_SCRIPT_CONTENT)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let options = attrs
+            .get(KEY_SCRIPT_OPTIONS)
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let mut defined_attributes = Attribute::new();
+        defined_attributes.add_attr_from_serde(attrs);
+        for key in [KEY_SCRIPT_CONTENT, KEY_SCRIPT_OPTIONS, KEY_SCRIPT_PLACEMENT] {
+            defined_attributes.remove_attr_by_name(key);
+        }
+
+        let mut renderable = Renderable::default();
+        renderable.set_type(RenderableType::Element);
+        renderable.set_tag("script".to_string());
+        renderable.set_attributes(defined_attributes);
+        // Avoid closing the script tag or opening a comment from the code or
+        // the options.
+        let escape = |code: &str| code.replace("</", "<\\/").replace("<!--", "<\\!--");
+        renderable.element_content = format!(
+            "(function (options) {{\n{}\n}})({});",
+            escape(content),
+            escape(&options.to_string())
+        );
+        renderable.render();
+
         renderable
     }
 
@@ -401,9 +580,9 @@ impl Renderable {
 
         let mut obj_attributes = Attribute::new();
 
-        obj_attributes.build_scoped(&self.data);
+        obj_attributes.build_scoped_with_options(&self.data, &self.options);
         if !attributes.is_null() {
-            obj_attributes.build_attributes(attributes);
+            obj_attributes.build_attributes_with_policy(attributes, &self.options.policy);
         }
 
         self.fields
@@ -656,6 +835,58 @@ pub fn add_attr_to_json_element(
     minijinja::Value::from_serialize(&data)
 }
 
+/// Updates attributes of a JSON element or component by name.
+///
+/// # Arguments
+///
+/// * `v` - A reference to the `minijinja::Value` of the element or component.
+/// * `names` - The names of the attributes read and written back.
+/// * `update` - A closure updating these attributes as an [`Attribute`].
+///
+/// # Returns
+///
+/// * `minijinja::Value` - The renderable with the updated attributes, removed when missing.
+pub fn update_attributes_in_json(
+    v: &minijinja::Value,
+    names: &[String],
+    update: impl FnOnce(&mut Attribute),
+) -> minijinja::Value {
+    let mut data = convert_to_map(v);
+
+    let attributes = match get_renderable_type_from(v) {
+        RenderableType::Component => {
+            let key_attributes = KEY_PREFIX.to_string() + KEY_ATTRIBUTES;
+            let key_to_update = match data.contains_key(KEY_ATTRIBUTES) {
+                true => KEY_ATTRIBUTES.to_string(),
+                false => key_attributes,
+            };
+            let attributes = data.entry(key_to_update).or_insert_with(|| json!({}));
+            match attributes.as_object_mut() {
+                Some(attributes) => attributes,
+                None => return minijinja::Value::from_serialize(&data),
+            }
+        }
+        _ => &mut data,
+    };
+
+    let mut attribute = Attribute::new();
+    for name in names {
+        if let Some(value) = attributes.get(name) {
+            attribute.add_attr_from_serde(&json!({ name: value }));
+        }
+    }
+    update(&mut attribute);
+
+    for name in names {
+        match attribute.get_value(name) {
+            Some(value) => attributes.insert(name.clone(), value.clone().into()),
+            None => attributes.remove(name),
+        };
+    }
+
+    minijinja::Value::from_serialize(&data)
+}
+
 fn add_or_merge_attr(
     data: &mut Map<String, serde_json::Value>,
     key: &str,
@@ -695,11 +926,41 @@ fn add_or_merge_attr(
     data.insert(key.to_string(), serde_json::Value::from(merged_values));
 }
 
+/// Add the Subresource Integrity to a library `link` or `script` attributes.
+///
+/// The hash is taken from the design system `LIBRARIES_INTEGRITY` unless
+/// already declared. `crossorigin` is required by browsers to check the
+/// integrity of cross origin resources.
+fn add_integrity(attributes: &mut Attribute, url: &str) {
+    if !attributes.has_attribute("integrity".into()) {
+        match LIBRARIES_INTEGRITY.get(url) {
+            Some(hash) => attributes.add_attr("integrity", vec![hash]),
+            None => return,
+        }
+    }
+    if !attributes.has_attribute("crossorigin".into()) {
+        attributes.add_attr("crossorigin", vec!["anonymous"]);
+    }
+}
+
 fn convert_to_map(value: &minijinja::Value) -> Map<String, serde_json::Value> {
     let json = serde_json::to_string(value).unwrap();
     serde_json::from_str(&json).unwrap()
 }
 
+/// Keep a debug annotation from closing the HTML comment.
+fn comment_safe(annotation: &str) -> String {
+    let mut output = String::with_capacity(annotation.len());
+    for char in annotation.chars() {
+        if char == '>' || char.is_whitespace() || (char == '-' && output.ends_with('-')) {
+            continue;
+        }
+        output.push(char);
+    }
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -862,6 +1123,61 @@ mod tests {
         assert!(script.output.contains("async"));
     }
 
+    #[test]
+    fn test_script_integrity() {
+        let script = Renderable::script("https://cdn.dilla.io/test.remote.js", &json!({}));
+        assert!(script.output.contains(
+            "integrity=\"sha384-oqVuAfXRKap7fdgcCY5uykM6+R9GqQ8K/uxy9rx7HNQlGYl1kPzQho1wx4JwY8wC\""
+        ));
+        assert!(script.output.contains("crossorigin=\"anonymous\""));
+
+        let attrs = json!({"integrity": "sha384-foo", "crossorigin": "use-credentials"});
+        let link = Renderable::link("https://cdn.dilla.io/other.css", &attrs);
+        assert!(link.output.contains("integrity=\"sha384-foo\""));
+        assert!(link.output.contains("crossorigin=\"use-credentials\""));
+        assert!(!link.output.contains("anonymous"));
+
+        let script = Renderable::script("local.js", &json!({}));
+        assert!(!script.output.contains("integrity"));
+    }
+
+    #[test]
+    fn test_script_module_fallback() {
+        let attrs = json!({"type": "module", "fallback": "app.legacy.js", "placement": "head"});
+        let script = Renderable::script("app.js", &attrs);
+        assert_eq!(
+            script.output,
+            "<script src=\"app.js\" type=\"module\"></script>\n<script src=\"app.legacy.js\" nomodule></script>"
+        );
+    }
+
+    #[test]
+    fn test_inline_script() {
+        let attrs = json!({
+            "script": "init(options.selector);",
+            "options": {"selector": "</script>"},
+            "placement": "body"
+        });
+        let script = Renderable::inline_script(&attrs);
+        assert_eq!(
+            script.output,
+            "<script>(function (options) {\ninit(options.selector);\n})({\"selector\":\"<\\/script>\"});</script>"
+        );
+    }
+
+    #[test]
+    fn test_inline_script_content_escaped() {
+        let attrs = json!({
+            "script": "document.write('<!--<script></script><img src=x onerror=alert(1)>');"
+        });
+        let script = Renderable::inline_script(&attrs);
+        assert_eq!(
+            script.output,
+            "<script>(function (options) {\ndocument.write('<\\!--<script><\\/script><img src=x onerror=alert(1)>');\n})({});</script>"
+        );
+        assert_eq!(script.output.matches("</script>").count(), 1);
+    }
+
     #[test]
     fn should_add_single_attribute_with_single_class() {
         let mut renderable = Renderable::default();
@@ -1,14 +1,32 @@
 //! Dilla renderer to output the result.
 
 use crate::bubbable::Bubbable;
-use crate::renderable::{Html, Renderable};
-use crate::{engine, DEFINITION};
+use crate::bundle::Bundle;
+use crate::dom::{self, Node};
+use crate::email::EmailInliner;
+use crate::engine::MissingTranslation;
+use crate::format::{self, push_line};
+use crate::hints::ResourceHints;
+use crate::limits;
+use crate::locale::{self, KEY_LOCALE, KEY_TRANSLATION};
+use crate::options::RenderOptions;
+use crate::output::{self, OutputFormat};
+use crate::renderable::{Html, Renderable, KEY_SCRIPT_PLACEMENT};
+use crate::scoped;
+use crate::source_map::{self, SourceMap};
+use crate::timing::{self, TimingNode};
+use crate::{engine, Error, DEFINITION, KEY_PREFIX};
 
 use indexmap::IndexMap;
 use minijinja::{context, Environment, HtmlEscape};
 use serde::Serialize;
 use serde_json::{json, Map, Value};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+
+/// Top-level item property to identify a fragment.
+const KEY_ID: &str = "id";
 
 /// Wrap the Render to build the HTML markup.
 #[derive(Debug, Default, Serialize)]
@@ -23,9 +41,25 @@ pub struct RendererWrapper {
     pub stylesheet: String,
     #[serde(with = "indexmap::map::serde_seq")]
     pub javascript_src: IndexMap<String, Value>,
+    /// Inline scripts from the payload as `name => {script, options}`.
+    #[serde(with = "indexmap::map::serde_seq")]
+    pub javascript_inline: IndexMap<String, Value>,
+    /// Resource hints for the libraries, with option `resource_hints`.
+    pub hints: ResourceHints,
+    /// Payload pointers of the body ranges, with option `source_map`.
+    pub source_map: SourceMap,
+    /// Render times by component, with option `profiling`.
+    pub timing: TimingNode,
+    /// Terms of `|t` without translation, with their template.
+    pub missing_translations: Vec<MissingTranslation>,
     // Internal container for the string version of js to use on non json output.
     system_javascript: String,
     javascript: String,
+    // Internal container for the position in body of each top-level item.
+    fragments: Vec<(Option<String>, Range<usize>)>,
+    // Internal container for js with placement `head`.
+    system_javascript_head: String,
+    javascript_head: String,
 }
 
 impl RendererWrapper {
@@ -34,24 +68,23 @@ impl RendererWrapper {
     }
 
     pub fn add_body(&mut self, body: &str) {
-        self.body.push_str(&format!("\n{}", body));
+        push_line(&mut self.body, body);
     }
 
     pub fn add_head(&mut self, head: &str) {
-        self.head.push_str(&format!("\n{}", head));
+        push_line(&mut self.head, head);
     }
 
     pub fn add_style(&mut self, style: &str) {
-        self.style.push_str(&format!("\n{}", style));
+        push_line(&mut self.style, style);
     }
 
     pub fn add_system_stylesheet(&mut self, stylesheet: &str) {
-        self.system_stylesheet
-            .push_str(&format!("\n{}", stylesheet));
+        push_line(&mut self.system_stylesheet, stylesheet);
     }
 
     pub fn add_system_javascript(&mut self, script: &str) {
-        self.system_javascript.push_str(&format!("\n{}", script));
+        push_line(&mut self.system_javascript, script);
     }
 
     pub fn add_system_javascript_src(&mut self, script_url: &str, data: Value) {
@@ -60,24 +93,90 @@ impl RendererWrapper {
     }
 
     pub fn add_stylesheet(&mut self, stylesheet: &str) {
-        self.stylesheet.push_str(&format!("\n{}", stylesheet));
+        push_line(&mut self.stylesheet, stylesheet);
     }
 
     pub fn add_javascript(&mut self, script: &str) {
-        self.javascript.push_str(&format!("\n{}", script));
+        push_line(&mut self.javascript, script);
     }
 
     pub fn add_javascript_src(&mut self, script_url: &str, data: Value) {
         self.javascript_src.insert(script_url.to_string(), data);
     }
 
+    pub fn add_system_javascript_head(&mut self, script: &str) {
+        push_line(&mut self.system_javascript_head, script);
+    }
+
+    pub fn add_javascript_head(&mut self, script: &str) {
+        push_line(&mut self.javascript_head, script);
+    }
+
+    pub fn add_javascript_inline(&mut self, name: &str, data: Value) {
+        self.javascript_inline.insert(name.to_string(), data);
+    }
+
+    pub fn add_fragment(&mut self, id: Option<String>, range: Range<usize>) {
+        self.fragments.push((id, range));
+    }
+
+    /// HTML of each top-level item as `(id, html)`, without empty items.
+    pub fn fragments(&self) -> Vec<(Option<&str>, &str)> {
+        self.fragments
+            .iter()
+            .map(|(id, range)| (id.as_deref(), self.body[range.clone()].trim()))
+            .filter(|(id, html)| id.is_some() || !html.is_empty())
+            .collect()
+    }
+
+    /// HTML `<script>` tags of the design system libraries for end of body.
+    pub fn system_javascript(&self) -> &str {
+        &self.system_javascript
+    }
+
+    /// HTML `<script>` tags of the payload libraries for end of body.
+    pub fn javascript(&self) -> &str {
+        &self.javascript
+    }
+
+    /// HTML `<script>` tags of the design system libraries for head.
+    pub fn system_javascript_head(&self) -> &str {
+        &self.system_javascript_head
+    }
+
+    /// HTML `<script>` tags of the payload libraries for head.
+    pub fn javascript_head(&self) -> &str {
+        &self.javascript_head
+    }
+
     /// Build Bubabble for this render.
-    pub fn build(&mut self, bubbable: Bubbable) {
-        self.build_system_library();
-        self.build_bubbable(bubbable);
+    pub fn build(&mut self, bubbable: Bubbable, options: &RenderOptions) {
+        self.build_system_library(options);
+        self.build_bubbable(bubbable, options);
+        if options.resource_hints {
+            self.build_hints();
+        }
+    }
+
+    /// Build resource hints from the collected libraries and prepend to head.
+    fn build_hints(&mut self) -> &mut Self {
+        let mut hints = ResourceHints::new();
+        hints.add_stylesheets(&self.system_stylesheet);
+        hints.add_stylesheets(&self.stylesheet);
+        hints.add_scripts(&self.system_javascript_src);
+        hints.add_scripts(&self.javascript_src);
+
+        if !hints.is_empty() {
+            let mut head = hints.to_html_string();
+            push_line(&mut head, &self.head);
+            self.head = head;
+        }
+        self.hints = hints;
+
+        self
     }
 
-    fn build_system_library(&mut self) -> &mut Self {
+    fn build_system_library(&mut self, options: &RenderOptions) -> &mut Self {
         // Get libraries defined by the design system (always loaded).
         let default_css = DEFINITION.default_libraries_css_html;
         self.add_system_stylesheet(default_css);
@@ -87,12 +186,16 @@ impl RendererWrapper {
             for (key, value) in phf_attributes {
                 attributes.insert(key.to_string(), Value::String(value.to_string()));
             }
+            add_nonce(&mut attributes, options);
             let new_attributes = Value::Object(attributes);
 
             // Create a string js with attributes for non json output.
             let js = Renderable::script(url, &new_attributes).to_html_string();
 
-            self.add_system_javascript(&js);
+            match is_head_script(&new_attributes) {
+                true => self.add_system_javascript_head(&js),
+                false => self.add_system_javascript(&js),
+            }
             // Add regular js map with attributes as object.
             self.add_system_javascript_src(url, new_attributes);
         }
@@ -100,18 +203,36 @@ impl RendererWrapper {
         self
     }
 
-    fn build_bubbable(&mut self, bubbable: Bubbable) -> &mut Self {
+    fn build_bubbable(&mut self, bubbable: Bubbable, options: &RenderOptions) -> &mut Self {
         let css = bubbable.library.css.join("\n");
         self.add_stylesheet(&css);
 
-        for (url, attributes) in bubbable.library.js {
+        for (url, mut attributes) in bubbable.library.js {
+            if let Some(map) = attributes.as_object_mut() {
+                add_nonce(map, options);
+            }
             // Create a string js with attributes for non json output.
             let js = Renderable::script(&url, &attributes).to_html_string();
-            self.add_javascript(&js);
+            match is_head_script(&attributes) {
+                true => self.add_javascript_head(&js),
+                false => self.add_javascript(&js),
+            }
             // Add regular js map with attributes as object.
             self.add_javascript_src(&url, attributes);
         }
 
+        for (name, mut script) in bubbable.library.inline {
+            if let Some(map) = script.as_object_mut() {
+                add_nonce(map, options);
+            }
+            let js = Renderable::inline_script(&script).to_html_string();
+            match is_head_script(&script) {
+                true => self.add_javascript_head(&js),
+                false => self.add_javascript(&js),
+            }
+            self.add_javascript_inline(&name, script);
+        }
+
         let attached_build = bubbable.attached_build.clone();
         self.add_head(&attached_build);
 
@@ -123,11 +244,33 @@ impl RendererWrapper {
         self
     }
 }
+
+/// Check a library script is placed in `head` instead of the end of `body`.
+fn is_head_script(attributes: &Value) -> bool {
+    attributes.get(KEY_SCRIPT_PLACEMENT).and_then(Value::as_str) == Some("head")
+}
+
+/// Add the CSP nonce, if any, to a script attributes map.
+fn add_nonce(attributes: &mut Map<String, Value>, options: &RenderOptions) {
+    if let Some(nonce) = &options.nonce {
+        attributes
+            .entry("nonce")
+            .or_insert_with(|| Value::String(nonce.to_owned()));
+    }
+}
+
 /// Simple render struct to process the data.
 #[derive(Debug, Default)]
 pub(crate) struct Renderer {
     pub output: RendererWrapper,
     pub translation: HashMap<String, String>,
+    pub options: RenderOptions,
+    /// DOM nodes of the body, with option `dom`.
+    pub dom: Vec<Node>,
+    /// Limit exceeded or template error that stopped the render.
+    pub error: Option<Error>,
+    /// Locale of the render, from option `locale` or the payload **@lang**.
+    pub locale: Option<String>,
 }
 
 impl Renderer {
@@ -135,55 +278,138 @@ impl Renderer {
         Renderer {
             output: RendererWrapper::new(),
             translation: HashMap::new(),
+            options: RenderOptions::default(),
+            dom: Vec::new(),
+            error: None,
+            locale: None,
+        }
+    }
+
+    pub fn with_options(options: RenderOptions) -> Self {
+        Renderer {
+            options,
+            ..Renderer::new()
         }
     }
 
+    /// Set the translations of the `|t` filter, shared by the whole render.
     pub fn set_translation(&mut self, translation: HashMap<String, String>) {
         self.translation = translation;
     }
 
     pub fn render(&mut self, json: &Value) {
+        if self.options.profiling {
+            timing::start();
+        }
+        limits::start(&self.options.limits);
+        engine::reset_unique_ids();
+        engine::reset_missing_translations();
+        engine::seed_random(self.options.seed.unwrap_or_else(|| payload_seed(json)));
         // First pass is to collect all bubbable from 'json' recursively.
         let mut bubbable: Bubbable = Bubbable::new();
         bubbable.collect(json);
         // @todo move from bubbable to renderer.
         bubbable.render_variables();
+        if self.options.scoped_local_variables {
+            bubbable.render_local_variables();
+        }
 
-        self.set_translation(bubbable.translation.clone());
-        self.output.build(bubbable);
+        self.locale = match &self.options.locale {
+            Some(locale) if !locale.trim().is_empty() => Some(locale::normalize(locale)),
+            _ => bubbable.lang.clone(),
+        };
+        let chain = locale::fallback_chain(
+            self.locale.as_deref().unwrap_or_default(),
+            self.options.fallback_locale.as_deref(),
+        );
+        self.set_translation(bubbable.translation_for(&chain));
+        self.output.build(bubbable, &self.options);
 
         let mut env: minijinja::Environment = engine::init_jinja_environnement();
+        self.options.add_to_env(&mut env);
+        env.add_global(KEY_LOCALE, self.locale.clone().unwrap_or_default());
+        env.add_global(
+            KEY_TRANSLATION,
+            minijinja::Value::from_serialize(&self.translation),
+        );
+        env.set_fuel(self.options.limits.template_fuel);
 
-        if json.is_array() {
-            self.do_render(json.as_array().unwrap(), &mut env);
-        } else {
-            self.do_render(&[json.to_owned()], &mut env);
+        // Only the source map needs its own copy of the payload to annotate.
+        let payload: Cow<Value> = match self.options.source_map {
+            true => {
+                let mut payload = json.to_owned();
+                source_map::annotate(&mut payload, "");
+                Cow::Owned(payload)
+            }
+            false => Cow::Borrowed(json),
+        };
+        let items = match payload.as_ref() {
+            Value::Array(array) => array.as_slice(),
+            item => std::slice::from_ref(item),
+        };
+        self.render_items(items, &mut env);
+
+        if self.options.profiling {
+            self.output.timing = timing::finish();
+        }
+        self.output.missing_translations = engine::take_missing_translations();
+        engine::reset_random();
+        self.error = limits::finish();
+    }
+
+    /// Render the top-level items and keep their position in the body for
+    /// fragments.
+    fn render_items(&mut self, items: &[Value], env: &mut Environment) {
+        let _span = timing::span("render");
+        for item in items.iter() {
+            let start = self.output.body.len();
+            self.do_render(std::slice::from_ref(item), env);
+            if self.options.source_map {
+                self.output.source_map.extract(&mut self.output.body, start);
+            }
+            let id = item
+                .get(format!("{KEY_PREFIX}{KEY_ID}"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            self.output.add_fragment(id, start..self.output.body.len());
+            if !limits::check_output_size(self.output.body.len()) || limits::is_exceeded() {
+                break;
+            }
         }
     }
 
     /// Recursively render a serde_json Value.
     /// @todo is it a better place to wrap Value in a vec?
     pub fn do_render(&mut self, data: &[Value], env: &mut Environment) {
+        if limits::is_exceeded() {
+            return;
+        }
         for element in data.iter() {
             match element {
                 Value::String(string) => {
                     let escaped: String = HtmlEscape(string).to_string();
                     self.output.add_body(&escaped);
+                    self.add_text_node(string);
                 }
                 Value::Bool(boolean) => {
                     self.output.add_body(boolean.to_string().as_str());
+                    self.add_text_node(&boolean.to_string());
                 }
                 Value::Number(number) => {
                     self.output.add_body(number.to_string().as_str());
+                    self.add_text_node(&number.to_string());
                 }
                 Value::Array(array) => {
                     self.do_render(array, env);
                 }
                 Value::Object(obj) => {
-                    let ctx = context! { _translation => self.translation };
+                    let Some(_depth) = limits::enter() else {
+                        return;
+                    };
                     let mut renderable = Renderable::new(obj.to_owned());
-                    renderable.build_with_env(env, ctx);
+                    renderable.build_with_env(env, context! {});
                     self.output.add_body(&renderable.to_html_string());
+                    self.dom.extend_from_slice(renderable.nodes());
                 }
                 _ => {
                     // @todo [devtools] log something
@@ -194,31 +420,160 @@ impl Renderer {
     }
 }
 
+impl Renderer {
+    /// Whole HTML page with libraries inlined, used for offline export.
+    fn to_inline_string(&self, style: &str) -> String {
+        let mut bundle = Bundle::new(
+            self.options.asset_root.as_deref(),
+            &self.options.nonce_attribute(),
+        );
+        bundle.add_stylesheets(&self.output.system_stylesheet);
+        bundle.add_stylesheets(&self.output.stylesheet);
+        bundle.add_scripts(&self.output.system_javascript_src);
+        bundle.add_scripts(&self.output.javascript_src);
+        bundle.add_inline_scripts(&self.output.javascript_inline);
+
+        format!(
+            r#"<!DOCTYPE html>
+                <html{}>
+                    <head>
+                        {}
+                        {}
+                        {}
+                    </head>
+                    <body>
+                        {}
+                        {}
+                    </body>
+                </html>"#,
+            locale::html_attributes(self.locale.as_deref()),
+            self.output.head,
+            bundle.head,
+            style,
+            self.output.body,
+            bundle.body,
+        )
+    }
+}
+
+impl Renderer {
+    /// Whole HTML page for email clients, table based with inline styles.
+    fn to_email_string(&self) -> String {
+        let mut bundle = Bundle::new(self.options.asset_root.as_deref(), "");
+        let mut css = bundle.read_stylesheets(&self.output.system_stylesheet);
+        css.push_str(&bundle.read_stylesheets(&self.output.stylesheet));
+        css.push_str(&self.output.style);
+
+        let inliner = EmailInliner::new(&css);
+        let unsupported_css = inliner.unsupported_css();
+        let mut style: String = "".to_string();
+        if !unsupported_css.is_empty() {
+            style = format!("<style>\n{}</style>", unsupported_css);
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+                <html{}>
+                    <head>
+                        <meta charset="utf-8">
+                        <meta name="viewport" content="width=device-width, initial-scale=1">
+                        {}
+                        {}
+                    </head>
+                    <body style="margin: 0; padding: 0;">
+                        <table role="presentation" width="100%" cellpadding="0" cellspacing="0" border="0">
+                            <tr>
+                                <td>
+                                    {}
+                                </td>
+                            </tr>
+                        </table>
+                    </body>
+                </html>"#,
+            locale::html_attributes(self.locale.as_deref()),
+            inliner.inline(&self.output.head),
+            style,
+            inliner.inline(&self.output.body),
+        )
+    }
+}
+
+impl Renderer {
+    /// Json output with `fragments` list instead of `body`.
+    fn to_fragments_output(&self) -> Value {
+        let mut output = self.to_output();
+        if let Some(map) = output.as_object_mut() {
+            map.shift_remove("body");
+            map.shift_remove("source_map");
+            let fragments: Vec<Value> = self
+                .output
+                .fragments()
+                .into_iter()
+                .map(|(id, html)| json!({ "id": id, "html": self.format_html(html) }))
+                .collect();
+            map.insert("fragments".to_string(), Value::from(fragments));
+        }
+
+        output
+    }
+
+    /// Apply the `pretty` or `minify` option to an HTML part.
+    fn format_html(&self, html: &str) -> String {
+        if self.options.pretty {
+            format::pretty(html)
+        } else if self.options.minify {
+            format::minify(html)
+        } else {
+            html.to_string()
+        }
+    }
+
+    /// Serialize a json output, indented with the `pretty` option.
+    fn to_json_string(&self, output: &Value) -> String {
+        match self.options.pretty {
+            true => serde_json::to_string_pretty(output),
+            false => serde_json::to_string(output),
+        }
+        .unwrap_or_else(|_| "".to_string())
+    }
+
+    fn add_text_node(&mut self, text: &str) {
+        if self.options.dom {
+            self.dom.extend(Node::text(text));
+        }
+    }
+}
+
 impl Output for Renderer {
     // @todo ideally we want to work only with serde_json::Value or minijinja::value::Value
-    fn to_output_string(&self, output: &str) -> String {
+    fn to_output_string(&self, output: &OutputFormat) -> String {
         let mut style: String = "".to_string();
         if !self.output.style.is_empty() {
             style = format!(
                 r#"
-            <style>
+            <style{}>
                 {}
             </style>
             "#,
+                self.options.nonce_attribute(),
                 self.output.style
             );
         }
 
         let response: String = match output {
-            "_test" => format!(
+            OutputFormat::Test => format!(
                 r#"
                 {}
                 {}
                 {}
+                {}
                 "#,
-                self.output.body, self.output.stylesheet, self.output.javascript,
+                self.output.body,
+                self.output.stylesheet,
+                self.output.javascript_head,
+                self.output.javascript,
             ),
-            "_test_full" => format!(
+            OutputFormat::TestFull => format!(
                 r#"
                 {}
                 {}
@@ -227,23 +582,29 @@ impl Output for Renderer {
                 {}
                 {}
                 {}
+                {}
+                {}
                 "#,
                 self.output.body,
                 self.output.head,
                 style,
                 self.output.system_stylesheet,
                 self.output.stylesheet,
+                self.output.system_javascript_head,
+                self.output.javascript_head,
                 self.output.system_javascript,
                 self.output.javascript,
             ),
-            "full" => format!(
+            OutputFormat::Full => format!(
                 r#"<!DOCTYPE html>
-                <html>
+                <html{}>
                     <head>
                         {}
                         {}
                         {}
                         {}
+                        {}
+                        {}
                     </head>
                     <body>
                         {}
@@ -251,47 +612,95 @@ impl Output for Renderer {
                         {}
                     </body>
                 </html>"#,
+                locale::html_attributes(self.locale.as_deref()),
                 self.output.head,
                 self.output.system_stylesheet,
                 self.output.stylesheet,
                 style,
+                self.output.system_javascript_head,
+                self.output.javascript_head,
                 self.output.body,
                 self.output.system_javascript,
                 self.output.javascript,
             ),
-            "json" => serde_json::to_string(&self.to_output()).unwrap_or_else(|_| "".to_string()),
-            "_logs" => "".to_string(),
-            _ => format!("<!-- Unknown output: {} -->", output),
+            OutputFormat::FullInline => self.to_inline_string(&style),
+            OutputFormat::Email => self.to_email_string(),
+            OutputFormat::Json => self.to_json_string(&self.to_output()),
+            OutputFormat::Fragments => self.to_json_string(&self.to_fragments_output()),
+            OutputFormat::Dom => self.to_json_string(&json!({
+                "attached": dom::parse(&self.output.head),
+                "body": self.dom,
+            })),
+            OutputFormat::Logs => "".to_string(),
+            OutputFormat::Custom(name) => match output::formatter(name) {
+                Some(formatter) => formatter.format(&self.output, &self.options),
+                None => "".to_string(),
+            },
         };
 
-        response
+        match output {
+            OutputFormat::Test
+            | OutputFormat::TestFull
+            | OutputFormat::Full
+            | OutputFormat::FullInline
+            | OutputFormat::Email => self.format_html(&response),
+            _ => response,
+        }
     }
 
     fn to_output(&self) -> Value {
-        json!({
-            "attached": self.output.head,
-            "body": self.output.body,
-            "system_stylesheet": self.output.system_stylesheet,
+        // Source map ranges are on the raw body.
+        let body = match self.options.source_map {
+            true => self.output.body.clone(),
+            false => self.format_html(&self.output.body),
+        };
+        let mut output = json!({
+            "attached": self.format_html(&self.output.head),
+            "body": body,
+            "system_stylesheet": self.format_html(&self.output.system_stylesheet),
             "system_javascript": self.output.system_javascript_src,
-            "stylesheet": self.output.stylesheet,
+            "stylesheet": self.format_html(&self.output.stylesheet),
             "javascript": self.output.javascript_src,
             "variables": self.output.style,
-        })
+        });
+
+        if !self.output.javascript_inline.is_empty() {
+            output["javascript_inline"] = json!(self.output.javascript_inline);
+        }
+        if !self.output.missing_translations.is_empty() {
+            output["missing_translations"] = json!(self.output.missing_translations);
+        }
+        if self.options.resource_hints {
+            output["hints"] = json!(self.output.hints);
+        }
+        if self.options.profiling {
+            output["timing"] = json!(self.output.timing);
+        }
+        if self.options.source_map {
+            output["source_map"] = json!(self.output.source_map);
+        }
+
+        output
     }
 }
 
 /// Generate the response based on output type.
 pub trait Output: std::fmt::Debug {
-    fn to_output_string(&self, output: &str) -> String;
+    fn to_output_string(&self, output: &OutputFormat) -> String;
     fn to_output(&self) -> Value;
 }
 
 impl std::fmt::Display for dyn Output {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.to_output_string("full"))
+        write!(f, "{}", self.to_output_string(&OutputFormat::Full))
     }
 }
 
+/// Seed of the render from the payload hash.
+fn payload_seed(json: &Value) -> u64 {
+    u64::from(scoped::fnv1a(json.to_string().as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -316,9 +725,9 @@ mod tests {
         ]);
 
         renderer.render(&json_input);
-        let output = renderer.to_output_string("full");
+        let output = renderer.to_output_string(&OutputFormat::Full);
 
-        let expected = "<!DOCTYPE html><html><head><meta name=\"test\" /><link type=\"text/css\" rel=\"stylesheet\" href=\"default-1.css\" crossorigin=\"anonymous\"><link type=\"text/css\" rel=\"stylesheet\" href=\"default-2.css\" crossorigin=\"anonymous\"></head><body><span>Hello, world!</span><script src=\"default-1.js\" async=\"true\"></script><script src=\"default-2.js\" async=\"true\"></script></body></html>";
+        let expected = "<!DOCTYPE html><html><head><meta name=\"test\" /><link type=\"text/css\" rel=\"stylesheet\" href=\"default-1.css\" crossorigin=\"anonymous\"><link type=\"text/css\" rel=\"stylesheet\" href=\"default-2.css\" crossorigin=\"anonymous\"></head><body><span>Hello, world!</span><script src=\"default-1.js\" async></script><script src=\"default-2.js\" async></script></body></html>";
 
         assert_eq!(trim_whitespace(expected), trim_whitespace(&output));
     }
@@ -342,12 +751,12 @@ mod tests {
         ]);
 
         renderer.render(&json_input);
-        let output = renderer.to_output_string("json");
+        let output = renderer.to_output_string(&OutputFormat::Json);
 
         let expected = json!({
-            "attached": "\n<meta name=\"test\" />\n",
-            "body": "\n<span>\nHello, world!</span>\n",
-            "system_stylesheet": "\n<link type=\"text/css\" rel=\"stylesheet\" href=\"default-1.css\" crossorigin=\"anonymous\">\n<link type=\"text/css\" rel=\"stylesheet\" href=\"default-2.css\" crossorigin=\"anonymous\">",
+            "attached": "<meta name=\"test\" />",
+            "body": "<span>Hello, world!</span>",
+            "system_stylesheet": "<link type=\"text/css\" rel=\"stylesheet\" href=\"default-1.css\" crossorigin=\"anonymous\">\n<link type=\"text/css\" rel=\"stylesheet\" href=\"default-2.css\" crossorigin=\"anonymous\">",
             "system_javascript": {
                 "default-1.js": {
                     "async": "true"
@@ -356,7 +765,7 @@ mod tests {
                     "async": "true"
                 }
             },
-            "stylesheet": "\n",
+            "stylesheet": "",
             "javascript": {},
             "variables": ""
         });
@@ -364,6 +773,669 @@ mod tests {
         assert_eq!(expected, output_value);
     }
 
+    #[test]
+    fn test_nonce_option() {
+        let options = RenderOptions::new().with_nonce("r4nd0m");
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!({
+            "@element": "span",
+            "@variables": {
+                ":root": {
+                    "var-1": "#fff"
+                }
+            },
+            "@library": {
+                "js": {
+                    "test.js": {}
+                }
+            }
+        });
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::Full);
+
+        assert!(output.contains("<style nonce=\"r4nd0m\">"));
+        assert!(output.contains("<script src=\"default-1.js\" async nonce=\"r4nd0m\">"));
+        assert!(output.contains("<script src=\"test.js\" nonce=\"r4nd0m\">"));
+
+        let output = renderer.to_output();
+        assert_eq!(output["javascript"]["test.js"]["nonce"], "r4nd0m");
+    }
+
+    #[test]
+    fn test_scoped_local_variables_option() {
+        let options = RenderOptions::new().with_scoped_local_variables(true);
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!({
+            "@element": "span",
+            "@local_variables": {
+                "var-1": "#222"
+            }
+        });
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::TestFull);
+
+        assert!(output.contains("<span class=\"dilla-lv-3690cbaa\">"));
+        assert!(output.contains(".dilla-lv-3690cbaa {\n  --var-1: #222;\n}"));
+        assert!(!output.contains("style=\""));
+    }
+
+    #[test]
+    fn test_resource_hints_option() {
+        let options = RenderOptions::new().with_resource_hints(true);
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!({
+            "@element": "span",
+            "@library": {
+                "js": {
+                    "https://cdn.dilla.io/module.js": {"type": "module"}
+                }
+            }
+        });
+
+        renderer.render(&json_input);
+        let output = renderer.to_output();
+
+        let expected = json!([
+            {"rel": "preconnect", "href": "https://cdn.dilla.io"},
+            {"rel": "preload", "href": "default-1.css", "as": "style", "crossorigin": "anonymous"},
+            {"rel": "preload", "href": "default-2.css", "as": "style", "crossorigin": "anonymous"},
+            {"rel": "modulepreload", "href": "https://cdn.dilla.io/module.js"}
+        ]);
+        assert_eq!(output["hints"], expected);
+        assert!(output["attached"]
+            .as_str()
+            .unwrap()
+            .starts_with("<link rel=\"preconnect\" href=\"https://cdn.dilla.io\" />"));
+    }
+
+    #[test]
+    fn test_full_inline_output() {
+        let root = format!("{}/tests/bundle", env!("CARGO_MANIFEST_DIR"));
+        let options = RenderOptions::new().with_asset_root(&root);
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!([
+            {
+                "@element": "span",
+                "@library": {
+                    "css": {
+                        "css/base.css": {}
+                    },
+                    "js": {
+                        "js/app.js": {"type": "module"}
+                    }
+                }
+            },
+            {
+                "@element": "span",
+                "@library": {
+                    "css": {
+                        "/css/base.css": {}
+                    }
+                }
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::FullInline);
+
+        assert_eq!(output.matches("body { color: #222; }").count(), 1);
+        assert!(output.contains("<script type=\"module\">\nconsole.log(\"<\\/script>\");"));
+        assert!(!output.contains("href=\"css/base.css\""));
+        // Default libraries are not in the asset root.
+        assert!(output.contains("<script src=\"default-1.js\" async></script>"));
+    }
+
+    #[test]
+    fn test_email_output() {
+        let root = format!("{}/tests/bundle", env!("CARGO_MANIFEST_DIR"));
+        let options = RenderOptions::new().with_asset_root(&root);
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!({
+            "@element": "p",
+            "class": "note",
+            "@content": "Hello",
+            "@variables": {
+                ":root": {
+                    "var-2": "#eee"
+                }
+            },
+            "@local_variables": {
+                "var-1": "#222"
+            },
+            "@library": {
+                "css": {
+                    "css/email.css": {}
+                },
+                "js": {
+                    "js/app.js": {}
+                }
+            }
+        });
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::Email);
+
+        assert!(output.contains(
+            "<p style=\"color: #222; background: #eee; border: 1px solid #333333;\" class=\"note\">"
+        ));
+        assert!(output.contains("<table role=\"presentation\""));
+        assert!(!output.contains("<script"));
+        assert!(!output.contains("<link"));
+        assert!(!output.contains("var("));
+    }
+
+    #[test]
+    fn test_dom_output() {
+        let options = RenderOptions::new().with_dom(true);
+        let mut renderer = Renderer::with_options(options);
+        let json_input = json!([
+            {
+                "@element": "div",
+                "class": "wrapper",
+                "@content": [
+                    "Hello",
+                    {
+                        "@template": "<b>{{ name }}</b>",
+                        "name": "world"
+                    }
+                ]
+            },
+            {
+                "@attached": {
+                    "@element": "meta",
+                    "name": "test"
+                }
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output: Value =
+            serde_json::from_str(&renderer.to_output_string(&OutputFormat::Dom)).unwrap();
+
+        let expected = json!({
+            "attached": [
+                {"type": "element", "tag": "meta", "attributes": {"name": "test"}}
+            ],
+            "body": [
+                {
+                    "type": "element",
+                    "tag": "div",
+                    "attributes": {"class": "wrapper"},
+                    "children": [
+                        {"type": "text", "text": "Hello"},
+                        {
+                            "type": "element",
+                            "tag": "b",
+                            "children": [{"type": "text", "text": "world"}]
+                        }
+                    ]
+                }
+            ]
+        });
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_fragments_output() {
+        let mut renderer = Renderer::new();
+        let json_input = json!([
+            {
+                "@id": "title",
+                "@element": "h1",
+                "@content": "Title"
+            },
+            {
+                "@attached": {
+                    "@element": "meta",
+                    "name": "test"
+                }
+            },
+            {
+                "@element": "p",
+                "@content": "Text",
+                "@library": {
+                    "js": {
+                        "foo.js": {}
+                    }
+                }
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output: Value =
+            serde_json::from_str(&renderer.to_output_string(&OutputFormat::Fragments)).unwrap();
+
+        assert_eq!(
+            output["fragments"],
+            json!([
+                {"id": "title", "html": "<h1>Title</h1>"},
+                {"id": null, "html": "<p>Text</p>"}
+            ])
+        );
+        assert!(output.get("body").is_none());
+        assert_eq!(output["javascript"], json!({"foo.js": {}}));
+        assert_eq!(output["attached"], "<meta name=\"test\" />");
+    }
+
+    #[test]
+    fn test_debug_option() {
+        let mut renderer = Renderer::with_options(RenderOptions::new().with_debug(true));
+        let json_input = json!([
+            {
+                "@component": "other",
+                "@variant": "variant",
+                "text": {"@element": "b", "@content": "Bold"}
+            },
+            {"@template": "<i>{{ text }}</i>", "text": "Italic"}
+        ]);
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::Test);
+
+        assert!(output.contains(
+            "<!-- dilla:start component=other variant=variant template=other.variant.jinja -->"
+        ));
+        assert!(output.contains("<!-- dilla:start element=b --><b>Bold</b><!-- dilla:end -->"));
+        assert!(
+            output.contains("<!-- dilla:start template=inline --><i>Italic</i><!-- dilla:end -->")
+        );
+        assert_eq!(output.matches("<!-- dilla:start").count(), 3);
+        assert_eq!(output.matches("<!-- dilla:end -->").count(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "profiling")]
+    fn test_profiling_option() {
+        let mut renderer = Renderer::with_options(RenderOptions::new().with_profiling(true));
+        let json_input = json!([
+            {"@component": "test", "label": {"@component": "other", "text": "Nested"}},
+            {"@component": "test", "label": "Simple"}
+        ]);
+
+        renderer.render(&json_input);
+        let output: Value =
+            serde_json::from_str(&renderer.to_output_string(&OutputFormat::Json)).unwrap();
+
+        let render = output["timing"]["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|child| child["name"] == "render")
+            .unwrap();
+        assert_eq!(render["children"][0]["name"], "test");
+        assert_eq!(render["children"][0]["count"], 2);
+        assert_eq!(render["children"][0]["children"][0]["name"], "other");
+        assert_eq!(render["children"][0]["children"][0]["count"], 1);
+        assert!(output["timing"]["total"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_limits_option() {
+        use crate::limits::{LimitError, RenderLimits};
+
+        let render = |limits: RenderLimits, json_input: Value| {
+            let mut renderer = Renderer::with_options(RenderOptions::new().with_limits(limits));
+            renderer.render(&json_input);
+            renderer
+        };
+        let nested = json!({"@element": "div", "@content": {"@element": "p", "@content": {"@element": "b"}}});
+
+        let renderer = render(RenderLimits::new().with_max_depth(3), nested.clone());
+        assert!(renderer.error.is_none());
+        assert_eq!(renderer.output.body, "<div><p><b></b></p></div>");
+
+        let renderer = render(RenderLimits::new().with_max_depth(2), nested);
+        assert!(matches!(
+            renderer.error,
+            Some(Error::Limit(LimitError::Depth(2)))
+        ));
+
+        let items = json!([{"@element": "hr"}, {"@element": "hr"}, {"@element": "hr"}]);
+        let renderer = render(RenderLimits::new().with_max_renderables(2), items.clone());
+        assert!(matches!(
+            renderer.error,
+            Some(Error::Limit(LimitError::Renderables(2)))
+        ));
+
+        let renderer = render(RenderLimits::new().with_max_output_size(8), items);
+        assert!(matches!(
+            renderer.error,
+            Some(Error::Limit(LimitError::OutputSize(8)))
+        ));
+
+        let endless = json!({"@template": "{% for i in range(1000) %}{% for j in range(1000) %}x{% endfor %}{% endfor %}"});
+        let renderer = render(RenderLimits::new().with_template_fuel(100), endless);
+        assert!(matches!(
+            renderer.error,
+            Some(Error::Limit(LimitError::TemplateFuel(100)))
+        ));
+
+        // Limits are reset for the next render.
+        let renderer = render(RenderLimits::new(), json!({"@template": "ok"}));
+        assert!(renderer.error.is_none());
+        assert_eq!(renderer.output.body, "ok");
+    }
+
+    #[test]
+    fn test_template_error() {
+        let render = |json_input: Value| {
+            let mut renderer = Renderer::new();
+            renderer.render(&json_input);
+            renderer
+        };
+
+        for template in [
+            "{% if %}",
+            "{{ undefined_function() }}",
+            "{% macro loop() %}{{ loop() }}{% endmacro %}{{ loop() }}",
+        ] {
+            let renderer = render(json!([{"@template": template}, "after"]));
+            assert!(
+                matches!(renderer.error, Some(Error::Template(_))),
+                "{template}"
+            );
+            assert_eq!(renderer.output.body, "");
+        }
+
+        let renderer = render(json!({"@template": "ok"}));
+        assert!(renderer.error.is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "random")]
+    fn test_seed_option() {
+        let render = |options: RenderOptions, json_input: Value| {
+            let mut renderer = Renderer::with_options(options);
+            renderer.render(&json_input);
+            renderer.output.body
+        };
+        let payload = json!([
+            {"@template": "{{ random() }}"},
+            {"@element": "p", "@content": {"@template": "{{ random() }}"}}
+        ]);
+
+        let first = render(RenderOptions::new(), payload.clone());
+        assert_eq!(first, render(RenderOptions::new(), payload.clone()));
+
+        // Numbers are unique in the render, nested renderables included.
+        let numbers: Vec<&str> = first
+            .split(|c: char| !c.is_ascii_digit() && c != '-')
+            .filter(|n| n.parse::<i32>().is_ok())
+            .collect();
+        assert_eq!(numbers.len(), 2);
+        assert_ne!(numbers[0], numbers[1]);
+
+        let seeded = render(RenderOptions::new().with_seed(42), payload.clone());
+        assert_eq!(
+            seeded,
+            render(RenderOptions::new().with_seed(42), payload.clone())
+        );
+        assert_ne!(seeded, render(RenderOptions::new().with_seed(43), payload));
+    }
+
+    #[test]
+    fn test_locale_option() {
+        let render = |options: RenderOptions, json_input: &Value| {
+            let mut renderer = Renderer::with_options(options);
+            renderer.render(json_input);
+            renderer
+        };
+        let payload = json!({
+            "@lang": "fr",
+            "@trans": {"en": {"Hello": "Hello!"}, "ar": {"Hello": "مرحبا"}},
+            "@template": "{{ 'Hello'|t }}"
+        });
+
+        let renderer = render(RenderOptions::new(), &payload);
+        assert_eq!(renderer.locale, Some("fr".to_string()));
+        assert_eq!(renderer.output.body, "Hello");
+        let output = renderer.to_output_string(&OutputFormat::Full);
+        assert!(output.contains("<html lang=\"fr\">"));
+
+        let renderer = render(RenderOptions::new().with_fallback_locale("en"), &payload);
+        assert_eq!(renderer.output.body, "Hello!");
+
+        let renderer = render(RenderOptions::new().with_locale("ar_EG"), &payload);
+        assert_eq!(renderer.locale, Some("ar-EG".to_string()));
+        assert_eq!(renderer.output.body, "مرحبا");
+        let output = renderer.to_output_string(&OutputFormat::Full);
+        assert!(output.contains("<html lang=\"ar-EG\" dir=\"rtl\">"));
+
+        let renderer = render(RenderOptions::new(), &json!({"@template": "{{ locale }}"}));
+        assert_eq!(renderer.locale, None);
+        assert!(renderer
+            .to_output_string(&OutputFormat::Full)
+            .contains("<html>"));
+    }
+
+    #[test]
+    fn test_nested_translation() {
+        let mut renderer = Renderer::new();
+        renderer.render(&json!({
+            "@trans": {"Hello": "Bonjour"},
+            "@element": "b",
+            "@content": {"@template": "{{ 'Hello'|t }} {{ {'@template': \"{{ 'Hello'|t }}\"} }}"}
+        }));
+
+        assert_eq!(renderer.output.body, "<b>Bonjour Bonjour</b>");
+    }
+
+    #[test]
+    fn test_missing_translations() {
+        let mut renderer = Renderer::new();
+        renderer.render(&json!({
+            "@trans": {"Hello": "Bonjour"},
+            "@template": "{{ 'Hello'|t }} {{ 'Bye'|t }} {{ 'Bye'|t }} {{ {'@template': \"{{ 'Hello'|t }}\"} }}"
+        }));
+
+        assert_eq!(renderer.output.body, "Bonjour Bye Bye Bonjour");
+        let output = renderer.to_output();
+        assert_eq!(
+            output["missing_translations"],
+            json!([{"term": "Bye", "template": "inline"}])
+        );
+
+        let mut renderer = Renderer::new();
+        renderer.render(&json!({"@template": "{{ 'Hello'|t }}"}));
+        let output = renderer.to_output();
+        assert_eq!(output["missing_translations"][0]["term"], "Hello");
+
+        let mut renderer = Renderer::new();
+        renderer.render(&json!({"@trans": {"Hello": "Bonjour"}, "@template": "{{ 'Hello'|t }}"}));
+        assert!(renderer.to_output().get("missing_translations").is_none());
+    }
+
+    #[test]
+    fn test_policy_option() {
+        use crate::policy::SecurityPolicy;
+
+        let mut renderer =
+            Renderer::with_options(RenderOptions::new().with_policy(SecurityPolicy::strict()));
+        let json_input = json!([
+            {"@template": "{{ 7 * 7 }}"},
+            {"@element": "script", "@content": "alert(1)"},
+            {
+                "@element": "a",
+                "href": "javascript:alert(1)",
+                "onclick": "alert(1)",
+                "title": "Link",
+                "@content": {"@element": "iframe", "src": "https://example.com"}
+            },
+            {"@component": "test", "label": "Safe", "attributes": {"onmouseover": "alert(1)", "data-id": "1"}},
+            {"@element": "script src=//evil.js"},
+            {"@element": "img src=x onerror=alert(1)"}
+        ]);
+
+        renderer.render(&json_input);
+
+        assert!(renderer
+            .output
+            .body
+            .starts_with("<a title=\"Link\"></a>\nTemplate: test.jinja"));
+        assert!(renderer
+            .output
+            .body
+            .contains("<div class=\"test template\" data-id=\"1\">"));
+        assert!(!renderer.output.body.contains("alert"));
+        assert!(!renderer.output.body.contains("evil"));
+        assert!(!renderer.output.body.contains("49"));
+    }
+
+    #[test]
+    fn test_invalid_element_tag() {
+        let mut renderer = Renderer::new();
+        let json_input = json!([
+            {"@element": ""},
+            {"@element": 1},
+            {"@element": "1div"},
+            {"@element": "-x"},
+            {"@element": "my-element", "@content": "Valid"}
+        ]);
+
+        renderer.render(&json_input);
+        assert_eq!(renderer.output.body, "<my-element>Valid</my-element>");
+    }
+
+    #[test]
+    fn test_source_map_option() {
+        let mut renderer = Renderer::with_options(RenderOptions::new().with_source_map(true));
+        let json_input = json!([
+            "Intro",
+            {
+                "@element": "div",
+                "@content": [
+                    {"@element": "b", "@content": "Bold"},
+                    {"@template": "<i>{{ text }}</i>", "text": "Italic"}
+                ]
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output: Value =
+            serde_json::from_str(&renderer.to_output_string(&OutputFormat::Json)).unwrap();
+        let body = output["body"].as_str().unwrap();
+        assert_eq!(body, "Intro\n<div><b>Bold</b>\n<i>Italic</i></div>");
+
+        let entries = output["source_map"].as_array().unwrap();
+        let ranges: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|entry| {
+                let start = entry["start"].as_u64().unwrap() as usize;
+                let end = entry["end"].as_u64().unwrap() as usize;
+                (
+                    entry["pointer"].as_str().unwrap(),
+                    entry["type"].as_str().unwrap(),
+                    &body[start..end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                ("/1", "element", "<div><b>Bold</b>\n<i>Italic</i></div>"),
+                ("/1/@content/0", "element", "<b>Bold</b>"),
+                ("/1/@content/1", "template", "<i>Italic</i>"),
+            ]
+        );
+        assert_eq!(entries[0]["name"], "div");
+        assert!(entries[2].get("name").is_none());
+    }
+
+    #[test]
+    fn test_source_map_option_formatted() {
+        for options in [
+            RenderOptions::new().with_minify(true),
+            RenderOptions::new().with_pretty(true),
+        ] {
+            let mut renderer = Renderer::with_options(options.with_source_map(true));
+            let json_input = json!([
+                "Intro",
+                {
+                    "@element": "div",
+                    "@content": [
+                        {"@element": "b", "@content": "Bold"},
+                        {"@template": "<i>{{ text }}</i>", "text": "Italic"}
+                    ]
+                }
+            ]);
+
+            renderer.render(&json_input);
+            let output: Value =
+                serde_json::from_str(&renderer.to_output_string(&OutputFormat::Json)).unwrap();
+            let body = output["body"].as_str().unwrap();
+            let slices: Vec<&str> = output["source_map"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| {
+                    let start = entry["start"].as_u64().unwrap() as usize;
+                    let end = entry["end"].as_u64().unwrap() as usize;
+                    &body[start..end]
+                })
+                .collect();
+            assert_eq!(
+                slices,
+                vec![
+                    "<div><b>Bold</b>\n<i>Italic</i></div>",
+                    "<b>Bold</b>",
+                    "<i>Italic</i>"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_minify_option() {
+        let mut renderer = Renderer::with_options(RenderOptions::new().with_minify(true));
+        let json_input = json!([
+            {
+                "@element": "ul",
+                "@content": [
+                    {"@element": "li", "@content": "One"},
+                    {"@element": "li", "@content": "Two"}
+                ]
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output: Value =
+            serde_json::from_str(&renderer.to_output_string(&OutputFormat::Json)).unwrap();
+        assert_eq!(output["body"], "<ul><li>One</li><li>Two</li></ul>");
+
+        let output = renderer.to_output_string(&OutputFormat::Full);
+        assert!(output.starts_with("<!DOCTYPE html><html><head><link"));
+        assert!(output.contains("<body><ul><li>One</li><li>Two</li></ul><script"));
+        assert!(!output.contains('\n'));
+    }
+
+    #[test]
+    fn test_pretty_option() {
+        let mut renderer = Renderer::with_options(RenderOptions::new().with_pretty(true));
+        let json_input = json!([
+            {
+                "@element": "ul",
+                "@content": [
+                    {"@element": "li", "@content": "One"},
+                    {"@element": "li", "@content": "Two"}
+                ]
+            }
+        ]);
+
+        renderer.render(&json_input);
+        let output = renderer.to_output_string(&OutputFormat::Json);
+        assert!(output.starts_with("{\n  \"attached\""));
+        let output: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            output["body"],
+            "<ul>\n  <li>One</li>\n  <li>Two</li>\n</ul>"
+        );
+
+        let output = renderer.to_output_string(&OutputFormat::Full);
+        assert!(output.contains("\n  <body>\n    <ul>\n      <li>One</li>\n"));
+    }
+
     fn trim_whitespace(s: &str) -> String {
         s.replace('\n', "").replace("  ", "")
     }
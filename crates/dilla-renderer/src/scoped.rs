@@ -10,7 +10,9 @@ const KEY_THEME: &str = "theme";
 const KEY_THEME_TARGET: &str = "target";
 const KEY_THEME_KEY: &str = "key";
 const KEY_THEME_VAL: &str = "val";
-const KEY_LOCAL_VARIABLES: &str = "local_variables";
+pub(crate) const KEY_LOCAL_VARIABLES: &str = "local_variables";
+/// Prefix of the class used to scope `@local_variables` in a stylesheet.
+const LOCAL_VARIABLES_CLASS_PREFIX: &str = "dilla-lv-";
 
 #[derive(Debug, Default)]
 pub struct Scoped {
@@ -91,6 +93,36 @@ impl Scoped {
         }
     }
 
+    /// Class name to scope the local variables in a stylesheet.
+    ///
+    /// The name is a hash of the variables declarations, so the same
+    /// declarations share the same class.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::{json, Map};
+    /// use dilla_renderer::scoped::Scoped;
+    ///
+    /// let mut scoped = Scoped::new();
+    /// assert_eq!(scoped.local_variables_class(), None);
+    ///
+    /// let mut data = Map::new();
+    /// data.insert(format!("@local_variables"), json!({"var-1": "#222"}));
+    /// scoped.collect(&data);
+    ///
+    /// assert_eq!(scoped.local_variables_class(), Some("dilla-lv-3690cbaa".to_string()));
+    /// ```
+    pub fn local_variables_class(&self) -> Option<String> {
+        if self.local_variables.is_empty() {
+            return None;
+        }
+
+        let hash = fnv1a(self.local_variables.join(" ").as_bytes());
+
+        Some(format!("{LOCAL_VARIABLES_CLASS_PREFIX}{hash:08x}"))
+    }
+
     /// Collects scoped styles from the provided data map.
     ///
     /// This looks for a key formatted as "{KEY_PREFIX}{KEY_STYLES}" in the data map.
@@ -149,3 +181,10 @@ impl Scoped {
         // @todo [devtools] log non valid theme
     }
 }
+
+/// Hash with FNV-1a, stable across platforms and builds unlike the std hasher.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x01000193)
+    })
+}
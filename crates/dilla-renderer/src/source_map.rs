@@ -0,0 +1,182 @@
+//! Map the rendered body back to the payload.
+//!
+//! With the render option `source_map`, every renderable of the payload is
+//! tagged with its JSON pointer before the render. The output of a tagged
+//! renderable is wrapped in private markers, kept through templates and
+//! nested slots, then removed from the body to record the byte ranges.
+
+use crate::{
+    renderable::{KEY_COMPONENT, KEY_ELEMENT, KEY_TEMPLATE},
+    KEY_PREFIX,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Hidden payload property with the JSON pointer of a renderable.
+pub(crate) const KEY_SOURCE: &str = "@_source";
+
+/// Markers from the Unicode private use area, not altered by the escape.
+const MARKER_START: char = '\u{E000}';
+const MARKER_SEPARATOR: char = '\u{E001}';
+const MARKER_END: char = '\u{E002}';
+
+/// A renderable of the payload and its range in the body.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// JSON pointer of the renderable in the payload.
+    pub pointer: String,
+    /// Renderable type as `component`, `element` or `template`.
+    #[serde(rename = "type")]
+    pub renderable_type: String,
+    /// Component name or element tag.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Byte range of the output in the body.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// List of source map entries ordered by start.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(transparent)]
+pub struct SourceMap {
+    entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn entries(&self) -> &[SourceMapEntry] {
+        &self.entries
+    }
+
+    /// Remove the markers of the body from a position and record the ranges.
+    pub(crate) fn extract(&mut self, body: &mut String, from: usize) {
+        let mut output = String::with_capacity(body.len() - from);
+        let mut open: Vec<usize> = Vec::new();
+        let mut chars = body[from..].chars();
+
+        while let Some(char) = chars.next() {
+            match char {
+                MARKER_START => {
+                    let mut read = || -> String {
+                        chars
+                            .by_ref()
+                            .take_while(|char| *char != MARKER_SEPARATOR)
+                            .collect()
+                    };
+                    let (pointer, renderable_type, name) = (read(), read(), read());
+                    open.push(self.entries.len());
+                    self.entries.push(SourceMapEntry {
+                        pointer,
+                        renderable_type,
+                        name: (!name.is_empty()).then_some(name),
+                        start: from + output.len(),
+                        end: from + output.len(),
+                    });
+                }
+                MARKER_END => {
+                    if let Some(index) = open.pop() {
+                        self.entries[index].end = from + output.len();
+                    }
+                }
+                _ => output.push(char),
+            }
+        }
+
+        // A renderable cut by a template filter ends with the body.
+        for index in open {
+            self.entries[index].end = from + output.len();
+        }
+
+        body.truncate(from);
+        body.push_str(&output);
+    }
+}
+
+/// Tag every renderable of the payload with its JSON pointer.
+pub(crate) fn annotate(value: &mut Value, pointer: &str) {
+    match value {
+        Value::Object(map) => {
+            let is_renderable = [KEY_COMPONENT, KEY_ELEMENT, KEY_TEMPLATE]
+                .iter()
+                .any(|key| map.contains_key(&format!("{KEY_PREFIX}{key}")));
+            if is_renderable {
+                map.insert(KEY_SOURCE.to_string(), Value::from(pointer));
+            }
+            for (key, value) in map.iter_mut() {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                annotate(value, &format!("{pointer}/{key}"));
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                annotate(value, &format!("{pointer}/{index}"));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Wrap the output of a renderable in the source markers.
+pub(crate) fn wrap(output: &str, pointer: &str, renderable_type: &str, name: &str) -> String {
+    format!(
+        "{MARKER_START}{pointer}{MARKER_SEPARATOR}{renderable_type}{MARKER_SEPARATOR}{name}{MARKER_SEPARATOR}{output}{MARKER_END}"
+    )
+}
+
+/// Remove the source markers of an output.
+pub(crate) fn strip(output: &str) -> String {
+    let mut source_map = SourceMap::new();
+    let mut output = output.to_string();
+    source_map.extract(&mut output, 0);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_annotate() {
+        let mut payload = json!([
+            {"@element": "p", "@content": {"@component": "alert", "a/b": {"@template": "x"}}},
+            "text"
+        ]);
+        annotate(&mut payload, "");
+
+        assert_eq!(payload[0][KEY_SOURCE], "/0");
+        assert_eq!(payload[0]["@content"][KEY_SOURCE], "/0/@content");
+        assert_eq!(
+            payload[0]["@content"]["a/b"][KEY_SOURCE],
+            "/0/@content/a~1b"
+        );
+    }
+
+    #[test]
+    fn test_extract_nested() {
+        let inner = wrap("<b>b</b>", "/0/@content", "element", "b");
+        let outer = wrap(&format!("<p>a{inner}</p>"), "/0", "component", "text");
+        let mut body = format!("<hr />{outer}");
+
+        let mut source_map = SourceMap::new();
+        source_map.extract(&mut body, 6);
+
+        assert_eq!(body, "<hr /><p>a<b>b</b></p>");
+        assert_eq!(source_map.entries()[0].pointer, "/0");
+        assert_eq!(
+            &body[source_map.entries()[0].start..source_map.entries()[0].end],
+            "<p>a<b>b</b></p>"
+        );
+        assert_eq!(source_map.entries()[1].name, Some("b".to_string()));
+        assert_eq!(
+            &body[source_map.entries()[1].start..source_map.entries()[1].end],
+            "<b>b</b>"
+        );
+    }
+}
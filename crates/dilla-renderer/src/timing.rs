@@ -6,8 +6,17 @@ cfg_if::cfg_if! {
     }
 }
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+#[cfg(feature = "profiling")]
+use std::cell::RefCell;
+
+#[cfg(not(target_family = "wasm"))]
+type Start = Instant;
+#[cfg(target_family = "wasm")]
+type Start = i8;
+
 #[derive(Debug, Clone)]
 pub struct Timing {
     time: f32,
@@ -86,3 +95,200 @@ pub fn s() -> i8 {
 pub fn t(_now: i8) -> f32 {
     0.0
 }
+
+/// Node of the timing tree, aggregated by name under the same parent.
+///
+/// Times are in milliseconds, always `0` on WASM.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimingNode {
+    pub name: String,
+    pub count: usize,
+    pub total: f32,
+    /// Time without the children.
+    #[serde(rename = "self")]
+    pub self_time: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TimingNode>,
+}
+
+impl TimingNode {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..TimingNode::default()
+        }
+    }
+
+    fn child(&mut self, name: &str) -> usize {
+        match self.children.iter().position(|child| child.name == name) {
+            Some(index) => index,
+            None => {
+                self.children.push(TimingNode::new(name));
+                self.children.len() - 1
+            }
+        }
+    }
+
+    fn node(&mut self, path: &[usize]) -> &mut TimingNode {
+        path.iter()
+            .fold(self, |node, index| &mut node.children[*index])
+    }
+
+    /// Compute the self times, children sorted by total time.
+    fn finish(&mut self) {
+        for child in self.children.iter_mut() {
+            child.finish();
+        }
+        let children_total: f32 = self.children.iter().map(|child| child.total).sum();
+        self.self_time = (self.total - children_total).max(0.0);
+        self.children.sort_by(|a, b| b.total.total_cmp(&a.total));
+    }
+
+    /// Flatten the tree as lines for a summary, the root is skipped.
+    pub fn lines(&self) -> Vec<Timing> {
+        let mut lines = Vec::new();
+        self.add_lines(&mut lines, 0);
+
+        lines
+    }
+
+    fn add_lines(&self, lines: &mut Vec<Timing>, level: i8) {
+        for child in self.children.iter() {
+            lines.push(Timing::new(
+                child.total,
+                level,
+                format!(
+                    "{} x{} (self {:.3} ms)",
+                    child.name, child.count, child.self_time
+                ),
+            ));
+            child.add_lines(lines, level + 1);
+        }
+    }
+}
+
+#[cfg(feature = "profiling")]
+#[derive(Default)]
+struct Profiler {
+    active: bool,
+    root: TimingNode,
+    stack: Vec<(usize, Start)>,
+}
+
+#[cfg(feature = "profiling")]
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+/// Start to record the spans of the current thread.
+pub(crate) fn start() {
+    #[cfg(feature = "profiling")]
+    PROFILER.with(|profiler| {
+        *profiler.borrow_mut() = Profiler {
+            active: true,
+            root: TimingNode::new("root"),
+            stack: Vec::new(),
+        };
+    });
+}
+
+/// Stop the record and get the timing tree.
+pub(crate) fn finish() -> TimingNode {
+    #[cfg(feature = "profiling")]
+    return PROFILER.with(|profiler| {
+        let mut profiler = std::mem::take(&mut *profiler.borrow_mut());
+        profiler.root.count = 1;
+        profiler.root.total = profiler.root.children.iter().map(|child| child.total).sum();
+        profiler.root.finish();
+        profiler.root
+    });
+
+    #[cfg(not(feature = "profiling"))]
+    TimingNode::new("root")
+}
+
+/// Guard of a timed span, recorded when dropped.
+pub(crate) struct Span {
+    #[cfg(feature = "profiling")]
+    active: bool,
+}
+
+/// Time a span named from the component, nested in the current span.
+pub(crate) fn span(name: &str) -> Span {
+    #[cfg(feature = "profiling")]
+    return PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        if !profiler.active {
+            return Span { active: false };
+        }
+        let path: Vec<usize> = profiler.stack.iter().map(|(index, _)| *index).collect();
+        let index = profiler.root.node(&path).child(name);
+        profiler.stack.push((index, s()));
+        Span { active: true }
+    });
+
+    #[cfg(not(feature = "profiling"))]
+    {
+        let _ = name;
+        Span {}
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        PROFILER.with(|profiler| {
+            let mut profiler = profiler.borrow_mut();
+            let Some((_, start)) = profiler.stack.last().copied() else {
+                return;
+            };
+            let path: Vec<usize> = profiler.stack.iter().map(|(index, _)| *index).collect();
+            profiler.stack.pop();
+            let node = profiler.root.node(&path);
+            node.count += 1;
+            node.total += t(start);
+        });
+    }
+}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_tree() {
+        start();
+        {
+            let _render = span("render");
+            for _ in 0..2 {
+                let _card = span("card");
+                let _button = span("button");
+            }
+            let _button = span("button");
+        }
+        let _ignored = span("collect");
+        drop(_ignored);
+        let root = finish();
+
+        assert_eq!(root.children.len(), 2);
+        let render = root
+            .children
+            .iter()
+            .find(|child| child.name == "render")
+            .unwrap();
+        assert_eq!(render.count, 1);
+        let card = render.children.iter().find(|c| c.name == "card").unwrap();
+        assert_eq!(card.count, 2);
+        assert_eq!(card.children[0].name, "button");
+        assert_eq!(card.children[0].count, 2);
+        assert_eq!(render.children.len(), 2);
+
+        // Spans are not recorded once finished.
+        let _span = span("none");
+        drop(_span);
+        assert!(finish().children.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+#[path = "../src/build/integrity.rs"]
+mod integrity;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+#[test]
+fn test_build_integrity() {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/bundle");
+    let mut declared = HashMap::new();
+    declared.insert("https://cdn.dilla.io/declared.js", "sha384-declared");
+    let urls = [
+        "css/base.css?v=1",
+        "https://cdn.dilla.io/declared.js",
+        "https://cdn.dilla.io/remote.js",
+        "/missing.js",
+    ];
+
+    let computed = integrity::compute(&urls, &declared, &assets);
+
+    assert!(computed.hashes["css/base.css?v=1"].starts_with("sha384-"));
+    assert_eq!(
+        computed.hashes["https://cdn.dilla.io/declared.js"],
+        "sha384-declared"
+    );
+    assert_eq!(computed.files, vec![assets.join("css/base.css")]);
+    assert_eq!(computed.missing.len(), 2);
+    assert_eq!(
+        computed.missing[0],
+        "[SRI] Missing integrity for remote library: https://cdn.dilla.io/remote.js"
+    );
+    assert!(computed.missing[1].starts_with("[SRI] Missing local library /missing.js"));
+}
@@ -119,3 +119,12 @@ fn test_engine_variables() {
 fn test_filter() {
     utils::test_loop("filter", "_test", ".html");
 }
+
+#[test]
+fn test_extract_translations() {
+    let terms = dilla_renderer::engine::extract_translations();
+
+    assert!(terms.contains(&"Hello @name!".to_string()));
+    assert!(terms.contains(&"Total: {total, number}".to_string()));
+    assert!(terms.windows(2).all(|pair| pair[0] < pair[1]));
+}
@@ -21,6 +21,8 @@ mod bindings;
 use bindings::Guest;
 
 use dilla_renderer::render as dilla_render;
+#[cfg(feature = "prettify")]
+use dilla_renderer::{options::RenderOptions, output::OutputFormat, render_with_options};
 use std::env;
 use std::fs;
 use std::path::Path;
@@ -28,11 +30,6 @@ use std::path::Path;
 #[cfg(feature = "describer")]
 use dilla_describer::describe as dilla_describe;
 
-#[cfg(feature = "prettify")]
-use html_minifier::minify;
-#[cfg(feature = "prettify")]
-use serde_json::Value;
-
 use dilla_renderer::DESIGN_SYSTEM;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -50,22 +47,13 @@ impl Guest for Component {
         #[cfg(feature = "debug")]
         info("Guest");
 
-        #[cfg(feature = "prettify")]
-        let value: Value = serde_json::from_str(&result).unwrap();
-        #[cfg(feature = "prettify")]
-        return serde_json::to_string_pretty(&value).unwrap();
-
-        #[cfg(not(feature = "prettify"))]
-        dilla_render(&payload, "json").unwrap_or("Dilla engine::render error!".to_string())
+        render_json(&payload)
     }
 
     fn render_html(payload: String) -> String {
         #[cfg(feature = "debug")]
         info("Guest");
-        #[cfg(feature = "prettify")]
-        return minify(result).expect("Failed to minify string");
-        #[cfg(not(feature = "prettify"))]
-        dilla_render(&payload, "full").unwrap_or("Dilla engine::render error!".to_string())
+        render_full(&payload)
     }
 
     fn describe(req: String) -> String {
@@ -160,13 +148,7 @@ fn main_render(name: &str, silent: &str) -> String {
     let payload = get_payload(name);
 
     if silent.is_empty() {
-        #[cfg(feature = "prettify")]
-        let value: Value = serde_json::from_str(&result).unwrap();
-        #[cfg(feature = "prettify")]
-        return serde_json::to_string_pretty(&value).unwrap();
-
-        #[cfg(not(feature = "prettify"))]
-        dilla_render(&payload, "json").unwrap_or("Dilla engine::render error!".to_string())
+        render_json(&payload)
     } else {
         dilla_render(&payload, "json").unwrap_or("Dilla engine::render error!".to_string());
         "".to_string()
@@ -175,11 +157,35 @@ fn main_render(name: &str, silent: &str) -> String {
 
 fn main_render_html(name: &str) -> String {
     let payload = get_payload(name);
+    render_full(&payload)
+}
 
+/// Render the json output, indented with feature `prettify`.
+fn render_json(payload: &str) -> String {
     #[cfg(feature = "prettify")]
-    return minify(result).expect("Failed to minify string");
+    let result = render_with_options(
+        payload,
+        OutputFormat::Json,
+        RenderOptions::new().with_pretty(true),
+    );
     #[cfg(not(feature = "prettify"))]
-    dilla_render(&payload, "full").unwrap_or("Dilla engine::render error!".to_string())
+    let result = dilla_render(payload, "json");
+
+    result.unwrap_or("Dilla engine::render error!".to_string())
+}
+
+/// Render the full output, minified with feature `prettify`.
+fn render_full(payload: &str) -> String {
+    #[cfg(feature = "prettify")]
+    let result = render_with_options(
+        payload,
+        OutputFormat::Full,
+        RenderOptions::new().with_minify(true),
+    );
+    #[cfg(not(feature = "prettify"))]
+    let result = dilla_render(payload, "full");
+
+    result.unwrap_or("Dilla engine::render error!".to_string())
 }
 
 fn main_describe(req: &str) -> String {
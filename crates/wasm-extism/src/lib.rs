@@ -18,8 +18,7 @@
 use extism_pdk::*;
 
 #[cfg(feature = "prettify")]
-use html_minifier::minify;
-
+use dilla_renderer::{options::RenderOptions, output::OutputFormat, render_with_options};
 use dilla_renderer::{render as dilla_render, DESIGN_SYSTEM};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -37,12 +36,17 @@ pub fn version() -> FnResult<String> {
 
 #[plugin_fn]
 pub fn render_html(payload: String) -> FnResult<String> {
-    let result = dilla_render(&payload, "full").unwrap();
-
     #[cfg(feature = "prettify")]
-    return Ok(minify(result.clone()).expect("Failed to minify string"));
+    let result = render_with_options(
+        &payload,
+        OutputFormat::Full,
+        RenderOptions::new().with_minify(true),
+    )
+    .unwrap();
 
     #[cfg(not(feature = "prettify"))]
+    let result = dilla_render(&payload, "full").unwrap();
+
     Ok(result)
 }
 